@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::{fs, net::SocketAddr};
+use monai_rules::{Action, GameState, board::BoardDefinition, policy, snapshot::Snapshot, state::{STARTING_MONEY, GO_BONUS, TURN_LIMIT}};
+
+pub const USAGE: &str = "-- [--config FILE] [--headless] [--players COUNT] [--rooms COUNT] [--bind ADDRESS] [--webrtc-bind ADDRESS] [--public-url URL] [--code CODE] [--board classic|generated|FILE] [--starting-money AMOUNT] [--go-salary AMOUNT] [--turn-limit TURNS] [--seed NUMBER] [--scoring winner|ranked|share|stalemate] [--log-dir FOLDER] [--snapshot-dir FOLDER] [--restore FILE]... [--grace SECONDS] [--turn-seconds SECONDS] [--timeout end_turn|bot] [--auto-reset]";
+
+// Everything a deployment might change, read from an optional TOML file and then overridden by flags
+#[derive(Resource, Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub headless: bool, // no window, rendering or egui
+    pub players: Option<usize>, // start as soon as this many players have joined
+    pub rooms: usize, // games running side by side, each with its own code
+    pub bind: String, // signalling
+    pub webrtc_bind: String,
+    pub public_url: String, // WebRTC address clients are told to use
+    pub code: Option<String>, // random unless given
+    pub board: String, // classic, generated, or a .toml/.json board file
+    pub starting_money: i32,
+    pub go_salary: i32,
+    pub turn_limit: usize,
+    pub seed: Option<u64>, // games and rooms derive theirs from it so runs repeat, random per game unless given
+    pub scoring: Scoring, // final rewards when the turn limit is hit
+    pub log_dir: String, // a .jsonl file per game, empty to turn logging off
+    pub snapshot_dir: String, // each room's game is saved here every turn, empty to only save from the menu
+    pub restore: Vec<String>, // snapshots to carry on, one room each
+    pub grace: f32, // seconds a disconnected player's seat is held before it forfeits
+    pub turn_seconds: f32, // the board plays whatever is left of a turn after this long, 0 for no limit
+    pub timeout: Timeout,
+    pub auto_reset: bool
+}
+
+// How players still standing at the turn limit are rewarded, ranked by net worth
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scoring {
+    Winner, // the richest gets the win reward, everyone else the bankruptcy one
+    #[default]
+    Ranked, // spread evenly from the win reward down to the bankruptcy one
+    Share, // the win reward scaled by each player's share of the total net worth
+    Stalemate // no final rewards
+}
+
+// What the board plays for someone whose turn ran out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Timeout {
+    #[default]
+    EndTurn, // rolls out of jail, raises only what's owed and ends the turn
+    Bot // buys and builds while keeping a reserve, see monai_rules::policy
+}
+
+pub const WIN_REWARD: f32 = 1000.0;
+pub const LOSS_REWARD: f32 = -1000.0;
+
+impl Scoring {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "winner" => Some(Scoring::Winner),
+            "ranked" => Some(Scoring::Ranked),
+            "share" => Some(Scoring::Share),
+            "stalemate" => Some(Scoring::Stalemate),
+            _ => None
+        }
+    }
+
+    // standings are seats and net worths, richest first. Tied players share the better rank
+    pub fn rewards(&self, standings: &[(usize, i32)]) -> Vec<(usize, f32)> {
+        let last = standings.len().saturating_sub(1).max(1) as f32;
+        let total: i32 = standings.iter().map(|(_, worth)| (*worth).max(0)).sum();
+
+        standings.iter().filter_map(|(seat, worth)| {
+            let rank = standings.iter().position(|(_, other)| other == worth).expect("Standing is in its own standings");
+            let reward = match self {
+                Scoring::Winner if rank == 0 => WIN_REWARD,
+                Scoring::Winner => LOSS_REWARD,
+                Scoring::Ranked => WIN_REWARD - (WIN_REWARD - LOSS_REWARD) * rank as f32 / last,
+                Scoring::Share if total == 0 => WIN_REWARD / standings.len() as f32,
+                Scoring::Share => WIN_REWARD * (*worth).max(0) as f32 / total as f32,
+                Scoring::Stalemate => return None
+            };
+            Some((*seat, reward))
+        }).collect()
+    }
+}
+
+impl Timeout {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "end_turn" => Some(Timeout::EndTurn),
+            "bot" => Some(Timeout::Bot),
+            _ => None
+        }
+    }
+
+    pub fn choose(&self, game: &GameState, seat: usize) -> Option<Action> {
+        match self {
+            Timeout::EndTurn => policy::end_turn(game, seat),
+            Timeout::Bot => policy::heuristic(game, seat)
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { // Σ ascii("monai") = 1096
+            headless: false,
+            players: None,
+            rooms: 1,
+            bind: "127.0.0.1:1095".to_string(),
+            webrtc_bind: "127.0.0.1:1096".to_string(),
+            public_url: "http://127.0.0.1:1096".to_string(),
+            code: None,
+            board: "classic".to_string(),
+            starting_money: STARTING_MONEY,
+            go_salary: GO_BONUS,
+            turn_limit: TURN_LIMIT,
+            seed: None,
+            scoring: Scoring::default(),
+            log_dir: "logs".to_string(),
+            snapshot_dir: "snapshots".to_string(),
+            restore: vec![],
+            grace: 60.0,
+            turn_seconds: 0.0,
+            timeout: Timeout::default(),
+            auto_reset: false
+        }
+    }
+}
+
+impl Config {
+    // args without the program name
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = match args.iter().position(|arg| arg == "--config") {
+            Some(index) => Self::load(args.get(index + 1).ok_or("--config needs a file")?)?,
+            None => Self::default()
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--config" => { value()?; } // already loaded
+                "--headless" => config.headless = true,
+                "--auto-reset" => config.auto_reset = true,
+                "--players" => config.players = Some(parse(arg, value()?)?),
+                "--rooms" => config.rooms = parse(arg, value()?)?,
+                "--log-dir" => config.log_dir = value()?.clone(),
+                "--snapshot-dir" => config.snapshot_dir = value()?.clone(),
+                "--restore" => config.restore.push(value()?.clone()),
+                "--grace" => config.grace = parse(arg, value()?)?,
+                "--turn-seconds" => config.turn_seconds = parse(arg, value()?)?,
+                "--timeout" => {
+                    let policy = value()?;
+                    config.timeout = Timeout::parse(policy).ok_or(format!("--timeout got {}, expected end_turn or bot", policy))?;
+                }
+                "--bind" => config.bind = value()?.clone(),
+                "--webrtc-bind" => config.webrtc_bind = value()?.clone(),
+                "--public-url" => config.public_url = value()?.clone(),
+                "--code" => config.code = Some(value()?.clone()),
+                "--board" => config.board = value()?.clone(),
+                "--starting-money" => config.starting_money = parse(arg, value()?)?,
+                "--go-salary" => config.go_salary = parse(arg, value()?)?,
+                "--turn-limit" => config.turn_limit = parse(arg, value()?)?,
+                "--seed" => config.seed = Some(parse(arg, value()?)?),
+                "--scoring" => {
+                    let policy = value()?;
+                    config.scoring = Scoring::parse(policy).ok_or(format!("--scoring got {}, expected winner, ranked, share or stalemate", policy))?;
+                }
+                _ => return Err(format!("unknown argument {}", arg))
+            }
+        }
+
+        if config.turn_limit == 0 { return Err("--turn-limit must be at least 1".to_string()); }
+        if config.rooms == 0 { return Err("--rooms must be at least 1".to_string()); }
+        if config.grace < 0.0 { return Err("--grace can't be negative".to_string()); }
+        if config.turn_seconds < 0.0 { return Err("--turn-seconds can't be negative".to_string()); }
+        if config.players == Some(0) { return Err("--players must be at least 1".to_string()); }
+        address("--bind", &config.bind)?;
+        address("--webrtc-bind", &config.webrtc_bind)?;
+
+        // files are read again when they're used, this only catches mistakes before the server is up
+        if !matches!(config.board.as_str(), "classic" | "generated") {
+            BoardDefinition::load(&config.board).map_err(|error| format!("--board {}", error))?;
+        }
+        for path in config.restore.iter() {
+            Snapshot::load(path).map_err(|error| format!("--restore {}", error))?;
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|error| format!("could not read config {}: {}", path, error))?;
+        toml::from_str(&text).map_err(|error| format!("could not parse config {}: {}", path, error))
+    }
+}
+
+// checked up front so a typo is reported like any other bad flag rather than panicking once the server starts
+fn address(arg: &str, value: &str) -> Result<SocketAddr, String> {
+    value.parse().map_err(|_| format!("{} got {}, which isn't an address like 127.0.0.1:1095", arg, value))
+}
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} got {}, which isn't a valid number", arg, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewards(scoring: Scoring, standings: &[(usize, i32)]) -> Vec<f32> {
+        scoring.rewards(standings).into_iter().map(|(_, reward)| reward).collect()
+    }
+
+    #[test]
+    fn ranked_spreads_from_win_to_loss() {
+        assert_eq!(rewards(Scoring::Ranked, &[(2, 900), (0, 500), (1, 100)]), vec![WIN_REWARD, 0.0, LOSS_REWARD]);
+        assert_eq!(Scoring::Ranked.rewards(&[(2, 900), (0, 500)]), vec![(2, WIN_REWARD), (0, LOSS_REWARD)]);
+    }
+
+    #[test]
+    fn ties_get_the_same_reward() {
+        assert_eq!(rewards(Scoring::Ranked, &[(0, 500), (1, 500), (2, 100)]), vec![WIN_REWARD, WIN_REWARD, LOSS_REWARD]);
+        assert_eq!(rewards(Scoring::Ranked, &[(0, 900), (1, 500), (2, 500)]), vec![WIN_REWARD, 0.0, 0.0]);
+        assert_eq!(rewards(Scoring::Winner, &[(0, 500), (1, 500), (2, 100)]), vec![WIN_REWARD, WIN_REWARD, LOSS_REWARD]);
+        assert_eq!(rewards(Scoring::Share, &[(0, 500), (1, 500)]), vec![WIN_REWARD / 2.0, WIN_REWARD / 2.0]);
+    }
+
+    #[test]
+    fn share_splits_evenly_with_nothing_to_share() {
+        assert_eq!(rewards(Scoring::Share, &[(0, 300), (1, 100), (2, -50)]), vec![750.0, 250.0, 0.0]);
+        assert_eq!(rewards(Scoring::Share, &[(0, 0), (1, -100)]), vec![WIN_REWARD / 2.0, WIN_REWARD / 2.0]);
+    }
+
+    #[test]
+    fn single_player_wins() {
+        for scoring in [Scoring::Winner, Scoring::Ranked, Scoring::Share] {
+            assert_eq!(rewards(scoring, &[(1, 40)]), vec![WIN_REWARD]);
+        }
+        assert!(rewards(Scoring::Stalemate, &[(1, 40), (0, 20)]).is_empty());
+    }
+}
@@ -1,146 +1,348 @@
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 use naia_bevy_server::{Server, CommandsExt, UserKey};
-use std::collections::HashMap;
-use rand::Rng;
-use crate::{menu::BoardConfiguration, state::{Tiles, Players, Code}, SQUARE_SIZE};
-use monai_store::{tile::{ServerSide, Group, Chance, Corner, Tile, Tier}, player::{Position, ServerPlayer, Money}, transfer::{StartGame, BoardUpdateChannel, SendPlayer}};
+use std::{collections::HashMap, path::Path, time::{SystemTime, UNIX_EPOCH}};
+use crate::{menu::BoardConfiguration, state::{Rooms, Room, Tiles, Players, Code, Game, GameState, broadcast}, SQUARE_SIZE};
+use monai_rules::{GameState as Rules, card, replay::{Log, Header, VERSION}, snapshot::Snapshot, state::Settings, tile::{self, TileKind, Levy}};
+use monai_store::{tile::{ServerSide, Title, Group, Railroad, Utility, Chance, Deck, Corner, Go, Tax, FreeParking, Jail, GoToJail, Tile, Tier}, player::{Position, ServerPlayer, Money}, transfer::{StartGame, BoardUpdateChannel, SendPlayer}};
 
-pub fn generate_board(
-    code: Res<Code>,
-    configuration: Res<BoardConfiguration>,
-    mut tiles: ResMut<Tiles>,
+// rooms are started from the menu or auto start, and again after an auto reset
+pub fn start_rooms(
+    mut configuration: ResMut<BoardConfiguration>,
+    mut rooms: ResMut<Rooms>,
 
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>, // missing when headless
+    mut materials: Option<ResMut<Assets<ColorMaterial>>>,
 
     mut server: Server,
     mut commands: Commands
 ) {
-    let radians = (360.0 / configuration.corners as f32).to_radians();
+    for (index, room) in rooms.list.iter_mut().enumerate() {
+        if room.state != GameState::Starting { continue; }
+
+        match room.restore.take() {
+            Some(snapshot) => restore_game(room, snapshot, &mut configuration, &mut meshes, &mut materials, &mut server, &mut commands),
+            None => new_game(room, index, &mut configuration, &mut meshes, &mut materials, &mut server, &mut commands)
+        }
+        room.games += 1;
+        room.state = GameState::InGame;
+        info!("Room {} started with {} players, seed {}", index, room.players.seats.len(), room.game.settings.seed);
+    }
+}
+
+fn new_game(
+    room: &mut Room,
+    index: usize,
+    configuration: &mut BoardConfiguration,
+
+    meshes: &mut Option<ResMut<Assets<Mesh>>>,
+    materials: &mut Option<ResMut<Assets<ColorMaterial>>>,
+
+    server: &mut Server,
+    commands: &mut Commands
+) {
+    // seated by name so the same players get the same seats, whatever order they joined in
+    let players = &mut room.players;
+    let mut seats: Vec<UserKey> = players.list.keys().cloned().collect();
+    seats.sort_by(|first, second| players.name[first].cmp(&players.name[second]));
+    players.seats = seats.into_iter().map(Some).collect();
+
+    let seed = match configuration.seed().ok().flatten() {
+        Some(base) => game_seed(base, index, room.games),
+        None => rand::random()
+    };
+
+    let (layout, decks) = match &configuration.definition {
+        Some(definition) => (definition.layout(), definition.decks()),
+        None => (tile::generate(configuration.corners as usize, (configuration.squares / configuration.corners) as usize), card::decks())
+    };
+    let settings = Settings {
+        starting_money: configuration.starting_money,
+        go_salary: configuration.go_salary,
+        turn_limit: configuration.turn_limit,
+        jackpot: configuration.jackpot,
+        seed
+    };
+
+    let header = Header {
+        version: VERSION,
+        settings: settings.clone(),
+        players: players.seats.iter().flatten().map(|key| players.name[key].clone()).collect(),
+        board: configuration.definition.clone(),
+        tiles: layout.clone(),
+        decks: decks.clone()
+    };
+    let game = Rules::new(layout, decks, players.seats.len(), settings);
+    room.log = open_log(configuration, &room.code, header.clone());
+    room.corners = configuration.corners as usize;
+    room.tiles.probability = configuration.landing.probabilities(&header.tiles, &header.decks);
+
+    generate_board(room, &header, meshes, materials, server, commands);
+    initialize_players(room, header, game, meshes, materials, server, commands);
+}
+
+// picks up where a snapshot left off, every seat still in the game has rejoined by now
+fn restore_game(
+    room: &mut Room,
+    snapshot: Snapshot,
+    configuration: &mut BoardConfiguration,
+
+    meshes: &mut Option<ResMut<Assets<Mesh>>>,
+    materials: &mut Option<ResMut<Assets<ColorMaterial>>>,
+
+    server: &mut Server,
+    commands: &mut Commands
+) {
+    let players = &mut room.players;
+    let mut rejoined: Vec<UserKey> = players.list.keys().cloned().collect();
+    players.seats = snapshot.header.players.iter().zip(&snapshot.game.players).map(|(name, state)| {
+        if state.bankrupt { return None; } // nobody came back for it, same as a seat that went bankrupt in the game
+        let found = rejoined.iter().position(|key| players.name[key] == *name).expect("Restored before every seat rejoined");
+        Some(rejoined.remove(found))
+    }).collect();
+
+    // the log carries on from the snapshot, so it still replays from its header
+    room.log = snapshot.log.and_then(|position| match Log::resume(&position.path, position.length) {
+        Ok(log) => Some(log),
+        Err(error) => {
+            warn!("Could not resume game log {}: {}", position.path.display(), error);
+            None
+        }
+    });
+
+    room.corners = snapshot.corners;
+    room.tiles.probability = configuration.landing.probabilities(&snapshot.header.tiles, &snapshot.header.decks);
+    if snapshot.game.auction.is_some() { // the bid time left isn't saved, so everyone still bidding gets a full timer
+        room.timer.0 = Timer::from_seconds(configuration.bid_seconds, TimerMode::Once);
+    }
+
+    generate_board(room, &snapshot.header, meshes, materials, server, commands);
+    initialize_players(room, snapshot.header, snapshot.game, meshes, materials, server, commands);
+}
+
+// A fixed seed still gives every room and game its own dice. The first game in the first room plays it unchanged,
+// so the seed logged for any game replays it when passed back on its own
+fn game_seed(base: u64, room: usize, game: usize) -> u64 {
+    let offset = ((room as u64) << 32) | game as u64;
+    base.wrapping_add(offset.wrapping_mul(0x9e37_79b9_7f4a_7c15)) // spread apart so neighbouring bases don't share games
+}
+
+// laid out on room.corners, the header only has the tiles
+fn generate_board(
+    room: &mut Room,
+    header: &Header,
+
+    meshes: &mut Option<ResMut<Assets<Mesh>>>,
+    materials: &mut Option<ResMut<Assets<ColorMaterial>>>,
+
+    server: &mut Server,
+    commands: &mut Commands
+) {
+    let (code, tiles) = (&room.code, &mut room.tiles);
+    let (corners, squares) = (room.corners as i32, header.tiles.len() as i32);
+    let (scale, placements) = placements(corners, squares);
+
+    for (index, transform) in placements.into_iter().enumerate() {
+        let color = header.board.as_ref()
+            .and_then(|definition| definition.tiles[index].color.as_deref())
+            .unwrap_or("#1e1e2e");
+
+        let mut entity_commands = commands.spawn_empty();
+        if let (Some(meshes), Some(materials)) = (meshes.as_mut(), materials.as_mut()) {
+            let corner = index % (squares / corners) as usize == 0;
+            entity_commands.insert(tile_bundle(scale, corner, color, transform, meshes, materials));
+        }
+        let entity = entity_commands.enable_replication(server).id();
+
+        server.room_mut(&code.game_room).add_entity(&entity);
+        tiles.list.push(entity);
+    }
+}
+
+// where each tile sits around a polygon, and the size they share
+pub fn placements(corners: i32, squares: i32) -> (f32, Vec<Transform>) {
+    let radians = (360.0 / corners as f32).to_radians();
     let radius = SQUARE_SIZE / 2.0;
-    let scale = (radius * (radians / 2.0).sin()) / (configuration.squares / configuration.corners) as f32;
+    let scale = (radius * (radians / 2.0).sin()) / (squares / corners) as f32;
 
     let x_start = -1.0 * radius * (radians / 2.0).sin();
     let y_start = -1.0 * radius * (radians / 2.0).cos();
 
     let mut reference_transform = Transform::from_xyz(x_start, y_start, 0.0);
+    let mut placements = vec![];
 
-    for _rotation  in 0..configuration.corners {
-        for tile in 0..(configuration.squares / configuration.corners) { 
-            let entity = commands.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(
-                    if tile == 0 {
-                        shape::Circle::new(scale).into()
-                    } else {
-                        shape::Quad::new(Vec2::new(scale, scale * 2.0)).into()
-                    }
-                ).into(),
-                material: materials.add(ColorMaterial::from(Color::hex("#1e1e2e").expect("Should be a hex color"))),
-                transform: reference_transform,
-                ..default()
-            }).enable_replication(&mut server).id();
-
-            server.room_mut(&code.game_room).add_entity(&entity);
-            tiles.list.push(entity);
-
+    for _rotation  in 0..corners {
+        for _tile in 0..(squares / corners) {
+            placements.push(reference_transform);
             reference_transform.translation += reference_transform.rotation * Vec3::X * scale * 2.0;
         }
 
         reference_transform.rotate_z(radians);
     }
+    (scale, placements)
 }
 
-pub fn initialize_players(
-    configuration: Res<BoardConfiguration>,
-
-    mut spaces: ResMut<Tiles>,
-    mut players: ResMut<Players>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+// corners are circles, everything else a rectangle pointing inwards
+pub fn tile_bundle(
+    scale: f32,
+    corner: bool,
+    color: &str,
+    transform: Transform,
 
-    mut server: Server,
-    mut commands: Commands
-) {
-    players.initial_player();
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>
+) -> MaterialMesh2dBundle<ColorMaterial> {
+    MaterialMesh2dBundle {
+        mesh: meshes.add(
+            if corner {
+                shape::Circle::new(scale).into()
+            } else {
+                shape::Quad::new(Vec2::new(scale, scale * 2.0)).into()
+            }
+        ).into(),
+        material: materials.add(ColorMaterial::from(Color::hex(color).expect("Should be a hex color"))),
+        transform,
+        ..default()
+    }
+}
 
-    spaces.tested_probability = vec![0; spaces.list.len()];
+pub fn token_bundle(
+    seat: usize,
 
-    let runs = spaces.list.len() as i32 * 300;
-    let mut last_tile = 0;
-    let mut random = rand::thread_rng();
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>
+) -> MaterialMesh2dBundle<ColorMaterial> {
+    MaterialMesh2dBundle {
+        mesh: meshes.add(
+            shape::Circle::new(5.0).into()
+        ).into(),
+        material: materials.add(ColorMaterial::from(Color::hex(match seat % 4 {
+            0 => "#cba6f7",
+            1 => "#eba0ac",
+            2 => "#fab387",
+            _ => "#cdd6f4"
+        }).expect("Should be a hex color"))),
+        ..default()
+    }
+}
 
-    for turns in 0..runs {
-        if turns % 30 == 0 { last_tile = 0; } 
+// components for the tiles generate_board spawned and for every seat, mirroring a game that may already be underway.
+// Landing probabilities are solved into room.tiles beforehand
+fn initialize_players(
+    room: &mut Room,
+    header: Header,
+    rules: Rules,
 
-        last_tile += random.gen_range(2..=12);
-        last_tile %= spaces.list.len();
+    meshes: &mut Option<ResMut<Assets<Mesh>>>,
+    materials: &mut Option<ResMut<Assets<ColorMaterial>>>,
 
-        spaces.tested_probability[last_tile] += 1;
-    }
+    server: &mut Server,
+    commands: &mut Commands
+) {
+    let (code, spaces, players) = (&room.code, &mut room.tiles, &mut room.players);
+    players.offered.clear();
+    players.ready = 0;
+    players.begun = false;
 
-    let mut current_group = -1;
-    let mut current_group_fill = 0;
+    let settings = &header.settings;
 
-    for tile in 0..spaces.list.len() { // convert this to .enumerate() later
+    for (tile, state) in header.tiles.iter().enumerate() {
         let mut entity_commands = commands.get_entity(spaces.list[tile]).expect("Ghost tile found");
-        entity_commands.insert(ServerSide::new(spaces.tested_probability[tile] as f32 / runs as f32, entity_commands.id().to_bits(), tile));
-        entity_commands.insert(Tile::new(Tier::None, None, 100));
+        entity_commands.insert(ServerSide::new(spaces.probability[tile], entity_commands.id().to_bits(), tile));
+        entity_commands.insert(Tile::new(Tier::None, None, state.cost));
+        if let Some(definition) = &header.board {
+            entity_commands.insert(Title::new(definition.tiles[tile].name.clone(), definition.tiles[tile].color.clone()));
+        }
 
-        let relative_tile = tile % (configuration.squares / configuration.corners) as usize;
-        if relative_tile == 0 { entity_commands.insert(Corner); continue; }
-        if relative_tile % 3 == 1 { entity_commands.insert(Chance); continue; }
+        match state.kind {
+            TileKind::Corner => { entity_commands.insert(Corner); }
+            TileKind::Go => { entity_commands.insert((Corner, Go::new(settings.go_salary))); }
+            TileKind::Jail => { entity_commands.insert((Corner, Jail)); }
+            TileKind::FreeParking => { entity_commands.insert((Corner, FreeParking::new(settings.jackpot.then_some(0)))); }
+            TileKind::GoToJail => { entity_commands.insert((Corner, GoToJail)); }
+            TileKind::Tax(Levy::Fixed(amount)) => { entity_commands.insert(Tax::new(amount, false)); }
+            TileKind::Tax(Levy::Percent(percent)) => { entity_commands.insert(Tax::new(percent, true)); }
+            TileKind::Railroad => { entity_commands.insert(Railroad::new(state.rent.to_vec())); }
+            TileKind::Utility => { entity_commands.insert(Utility::new(state.rent.to_vec())); }
+            TileKind::Chance(deck) => { entity_commands.insert(Chance::new(deck)); }
+            TileKind::Property => {}
+        }
 
-        if current_group_fill % 3 == 0 {
-            current_group += 1;
-            spaces.groups.push(vec![]);
+        if let Some(group) = state.group {
+            entity_commands.insert(Group::new(group));
+            if spaces.groups.len() <= group { spaces.groups.resize(group + 1, vec![]); } // board files can list groups in any order
+            spaces.groups[group].push(entity_commands.id());
         }
+    }
 
-        entity_commands.insert(Group::new(current_group as usize));
-        spaces.groups[current_group as usize].push(entity_commands.id());
-        current_group_fill += 1;
+    for (index, deck) in header.decks.iter().enumerate() {
+        let entity = commands
+            .spawn(Deck::new(index, deck.name.clone(), deck.cards.iter().map(|card| card.text.clone()).collect()))
+            .enable_replication(server)
+            .id();
+
+        server.room_mut(&code.game_room).add_entity(&entity);
+        spaces.decks.push(entity);
     }
 
-    for (index, entity) in players.list.values().enumerate() {
-        commands.get_entity(*entity).expect("Could not find a valid player in initialization")
-            .insert(Money::new(1000))
-            .insert(Position::new(spaces.list[0].to_bits()))
-            .insert(ServerPlayer::new(entity.to_bits(), index))
-            .insert(MaterialMesh2dBundle {
-                mesh: meshes.add(
-                    shape::Circle::new(5.0).into()
-                ).into(),
-                material: materials.add(ColorMaterial::from(Color::hex(match index % 4 {
-                    0 => "#cba6f7",
-                    1 => "#eba0ac",
-                    2 => "#fab387",
-                    _ => "#cdd6f4"
-                }).expect("Should be a hex color"))),
-                ..default()
-            });
+    for (index, key) in players.seats.iter().enumerate().filter_map(|(index, key)| key.map(|key| (index, key))) {
+        let entity = players.list[&key];
+        let state = &rules.players[index];
+        let mut entity_commands = commands.get_entity(entity).expect("Could not find a valid player in initialization");
+        entity_commands
+            .insert(Money::new(state.money))
+            .insert(Position::new(spaces.list[state.position].to_bits()))
+            .insert(ServerPlayer::new(entity.to_bits(), index));
+
+        if let (Some(meshes), Some(materials)) = (meshes.as_mut(), materials.as_mut()) {
+            entity_commands.insert(token_bundle(index, meshes, materials));
+        }
     }
 
-    server.broadcast_message::<BoardUpdateChannel, StartGame>(&StartGame);
+    broadcast(server, players, &StartGame { seed: settings.seed });
+
+    room.game = Game(rules);
+    room.header = Some(header);
+}
+
+// one file per game, named after the room and when it started
+fn open_log(configuration: &BoardConfiguration, code: &Code, header: Header) -> Option<Log> {
+    if configuration.log_dir.is_empty() { return None; }
+
+    let started = SystemTime::now().duration_since(UNIX_EPOCH).expect("Clock is before 1970").as_millis();
+    let path = Path::new(&configuration.log_dir).join(format!("{}-{}.jsonl", code.value, started));
+    match Log::create(&path, header) {
+        Ok(log) => {
+            info!("Logging game to {}", path.display());
+            Some(log)
+        }
+        Err(error) => {
+            warn!("Could not create game log {}: {}", path.display(), error);
+            None
+        }
+    }
 }
 
 pub fn reset_game(
-    spaces: &mut ResMut<Tiles>,
-    players: &mut ResMut<Players>,
-    code: &Res<Code>,
+    spaces: &mut Tiles,
+    players: &mut Players,
+    code: &Code,
 
     commands: &mut Commands,
     server: &mut Server,
 ) {
     // Clear entities
-    for entity in players.list.values().chain(spaces.list.iter()) {
+    for entity in players.list.values().chain(spaces.list.iter()).chain(spaces.decks.iter()) {
         commands.get_entity(*entity).expect("Couldn't find listed entity").despawn_recursive();
     }
     
     // Clear lists
     players.list = HashMap::new();
-    players.current = None;
+    players.seats = vec![];
+    players.offered.clear();
     spaces.groups = vec![];
-    spaces.tested_probability = vec![];
+    spaces.probability = vec![];
     spaces.list = vec![];
+    spaces.decks = vec![];
 
     for key in players.bankrupt.drain(..).collect::<Vec<UserKey>>().into_iter() {
         let entity = commands
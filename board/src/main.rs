@@ -1,55 +1,88 @@
-//use std::env;
-use bevy::{prelude::*};
+use std::{env, time::Duration};
+use bevy::{prelude::*, app::ScheduleRunnerSettings, log::LogPlugin};
 use naia_bevy_server::{Plugin as ServerPlugin, ReceiveEvents, ServerConfig};
 use monai_store::protocol_builder;
 
+mod config;
 mod server;
 mod state;
 mod menu;
 mod generator;
 mod message;
 mod render;
+mod viewer;
 
 const SQUARE_SIZE: f32 = 720.0;
 
 fn main() {
-    //let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match config::Config::from_args(&args) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("{}", config::USAGE);
+            std::process::exit(2);
+        }
+    };
 
     let mut app = App::new();
 
-    app.add_plugins(DefaultPlugins.set(
-            WindowPlugin {
-                primary_window: Some(Window {
-                    resolution: (SQUARE_SIZE, SQUARE_SIZE).into(),
+    if config.headless {
+        app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(1.0 / 60.0))) // don't spin a core while idle
+            .add_plugins(MinimalPlugins)
+            .add_plugin(LogPlugin::default());
+    } else {
+        app.add_plugins(DefaultPlugins.set(
+                WindowPlugin {
+                    primary_window: Some(Window {
+                        resolution: (SQUARE_SIZE, SQUARE_SIZE).into(),
+                        ..default()
+                    }),
                     ..default()
-                }),
-                ..default()
-            }
-        ))
-        .add_plugin(ServerPlugin::new(ServerConfig::default(), protocol_builder()))
-        //.add_plugin(bevy_egui::EguiPlugin)
-        .add_plugin(bevy_inspector_egui::quick::WorldInspectorPlugin::new())
+                }
+            ))
+            //.add_plugin(bevy_egui::EguiPlugin)
+            .add_plugin(bevy_inspector_egui::quick::WorldInspectorPlugin::new())
 
-        .add_startup_system(init_camera)
-        .add_state::<state::GameState>()
-        .add_system(menu::gui.in_set(OnUpdate(state::GameState::Menu)))
-        .add_systems(
-            (
-                generator::generate_board,
-                generator::initialize_players
+            .add_startup_system(init_camera)
+            .add_system(menu::gui)
+            .add_system(viewer::controls)
+            .init_resource::<viewer::Viewer>()
+
+            .insert_resource(ClearColor(Color::hex("#11111b").expect("This should be a valid hex value")))
+            .add_systems(
+                (
+                    render::render_position,
+                    render::render_owner,
+                    render::render_room
+                )
             )
-            .chain()
-            .in_schedule(OnEnter(state::GameState::InGame))
-        )
-        //.add_system(generator::reset_game.in_schedule(OnExit(state::GameState::InGame)))
+            .add_systems(
+                (
+                    viewer::playback,
+                    viewer::render_playback
+                )
+                .chain()
+                .before(render::render_position)
+            );
+    }
+
+    app.add_plugin(ServerPlugin::new(ServerConfig::default(), protocol_builder()))
+        .insert_resource(config)
+
         .add_systems(
             (
+                menu::auto_start,
+                generator::start_rooms,
                 message::message_receive,
+                message::game_event,
+                message::auction_timer,
+                message::away_timer,
+                message::turn_timer,
                 message::next_turn,
-                message::reward_player,
-                message::bankrupt_player,
+                message::sync_state,
             )
-            .in_set(OnUpdate(state::GameState::InGame))
+            .chain()
         )
 
         .add_systems(
@@ -64,28 +97,12 @@ fn main() {
         )
         .add_startup_system(server::initialize_server)
 
-        .add_system(
-            state::auto_reset
-                .in_set(OnUpdate(state::GameState::AutoReset))
-        )
+        .add_system(state::auto_reset)
 
-        .add_event::<message::AwardPlayer>()
         .add_event::<message::NextTurn>()
-        .add_event::<message::BankruptPlayer>()
-    
-        .insert_resource(ClearColor(Color::hex("#11111b").expect("This should be a valid hex value")))
-        .add_systems(
-            (
-                render::render_position,
-                render::render_owner
-            )
-        );
-
-    // if args.len() != 1 || args[0] != "headless" {
-    //     app.add_startup_system(init_camera);
-    // }
+        .add_event::<message::GameEvent>()
 
-    app.run();
+        .run();
 }
 
 pub fn init_camera(
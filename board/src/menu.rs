@@ -1,29 +1,118 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
-use crate::state::{Players, GameState, Code};
+use naia_bevy_server::Server;
+use crate::{state::{Rooms, GameState}, config::{Config, Scoring, Timeout}};
+use monai_rules::{landing, board::{BoardDefinition, BoardError}, snapshot::Snapshot};
 
 #[derive(Resource)]
 pub struct BoardConfiguration {
     pub polygonal_board: bool,
     pub corners: i32,
     pub squares: i32,
-    pub auto_reset: bool
+    pub auto_reset: bool,
+    pub bid_seconds: f32,
+    pub grace_seconds: f32, // a disconnected player's seat waits this long for them
+    pub turn_seconds: f32, // 0 for no turn timer
+    pub timeout: Timeout,
+    pub starting_money: i32,
+    pub go_salary: i32,
+    pub turn_limit: usize,
+    pub scoring: Scoring,
+    pub seed: String, // blank for a random seed every game, otherwise each game derives its own from it
+    pub log_dir: String, // every game is logged here, blank to turn logging off
+    pub snapshot_dir: String, // saved every turn, blank turns that off and menu saves land in the working folder
+    pub snapshot_file: String, // restored into a new room from the menu
+    pub snapshot_error: Option<String>,
+    pub jackpot: bool, // free parking house rule
+    pub preset: BoardPreset,
+    pub board_file: String, // .toml or .json, only used by BoardPreset::File
+    pub definition: Option<BoardDefinition>, // loaded from the preset on start, None for the generated layout
+    pub board_error: Option<String>,
+    pub landing: landing::Cache, // every layout played so far, restarts on the same board skip solving it again
+    pub start_players: usize // rooms waiting to auto start need this many players, only for their first game
 }
 
-pub fn gui(
-    players: Res<Players>,
-    code: Res<Code>,
+const MAX_SQUARES: i32 = 2000; // generated boards past this take too long to solve and draw
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoardPreset {
+    Classic,
+    Generated, // placeholder layout sized by the sliders
+    File
+}
 
+pub fn gui(
+    mut rooms: ResMut<Rooms>,
     mut configuration: ResMut<BoardConfiguration>,
-    mut game_state: ResMut<NextState<GameState>>,
 
-    mut contexts: EguiContexts
+    mut contexts: EguiContexts,
+    mut server: Server
 ) { 
-    egui::Area::new("Main Menu").show(contexts.ctx_mut(), |ui| {
-        ui.label("Server");
+    let rooms = &mut *rooms; // split borrows between the list and the shown room
+    egui::Window::new("Main Menu").show(contexts.ctx_mut(), |ui| {
+        ui.label("Rooms");
         ui.separator();
 
-        ui.label(&code.value);
+        let mut regenerate = None;
+        for (index, room) in rooms.list.iter_mut().enumerate() {
+            ui.horizontal(|row| {
+                row.label(egui::RichText::new(&room.code.value).size(48.0).strong().monospace());
+                if row.button("Regenerate").clicked() { regenerate = Some(index); }
+                if index != rooms.shown && row.button("Show").clicked() { rooms.shown = index; }
+                if room.state == GameState::InGame && row.button("Save").clicked() { room.save(&configuration.snapshot_dir, true); }
+            });
+            ui.label(format!("{:?}, players join with this code, anyone already in stays", room.state));
+            if room.restore.is_some() {
+                ui.label(format!("Restoring, waiting for {}", room.waiting_on().join(", ")));
+            }
+
+            for (_player, name) in room.players.name.iter() {
+                ui.horizontal(|row| {
+                    row.label(name);
+                });
+            }
+
+            let players = &room.players;
+            if room.state == GameState::Menu && room.restore.is_none() && !players.list.is_empty() && players.list.len() == players.name.len() && ui.button("Start").clicked() {
+                match load_board(&mut configuration) {
+                    Ok(()) => room.state = GameState::Starting,
+                    Err(error) => {
+                        error!("{}", error);
+                        configuration.board_error = Some(error.to_string());
+                    }
+                }
+            }
+            ui.separator();
+        }
+
+        // codes have to stay unique, so these wait until the list is no longer borrowed
+        if let Some(index) = regenerate {
+            let code = rooms.unique_code();
+            rooms.list[index].code.value = code;
+            info!("Room {} join code is {}", index, rooms.list[index].code.value);
+        }
+        if ui.button("New Room").clicked() {
+            let code = rooms.unique_code();
+            rooms.add(code, false, &mut server);
+        }
+        ui.horizontal(|row| {
+            row.label("Snapshot File");
+            row.text_edit_singleline(&mut configuration.snapshot_file);
+            if row.button("Restore").clicked() {
+                match Snapshot::load(configuration.snapshot_file.trim()) {
+                    Ok(snapshot) => {
+                        let code = rooms.unique_code();
+                        let index = rooms.add(code, false, &mut server);
+                        rooms.restore(index, snapshot);
+                        configuration.snapshot_error = None;
+                    }
+                    Err(error) => configuration.snapshot_error = Some(error.to_string())
+                }
+            }
+        });
+        if let Some(error) = &configuration.snapshot_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
         ui.spacing();
 
         ui.label("Board");
@@ -32,30 +121,117 @@ pub fn gui(
         ui.horizontal(|row| {
             row.checkbox(&mut configuration.polygonal_board, "Polygon");
             row.checkbox(&mut configuration.auto_reset, "Auto-Reset");
+            row.checkbox(&mut configuration.jackpot, "Free Parking Jackpot");
+        });
+        ui.horizontal(|row| {
+            row.selectable_value(&mut configuration.preset, BoardPreset::Classic, "Classic");
+            row.selectable_value(&mut configuration.preset, BoardPreset::Generated, "Generated");
+            row.selectable_value(&mut configuration.preset, BoardPreset::File, "File");
         });
-        if configuration.polygonal_board {
-            ui.add(egui::Slider::new(&mut configuration.corners, 4..=360).text("Corners"));
+        if configuration.preset == BoardPreset::File {
+            ui.horizontal(|row| {
+                row.label("Board File");
+                row.text_edit_singleline(&mut configuration.board_file);
+            });
         }
-        let step = configuration.corners as f64; // Arbitrary numbers for now, just making sure division is easy
-        let minimum = configuration.corners * 1;
-        let maximum = configuration.corners * 500;
-        ui.add(egui::Slider::new(&mut configuration.squares, minimum..=maximum).text("Squares").step_by(step));
-        ui.spacing();
+        if let Some(error) = &configuration.board_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        if configuration.preset == BoardPreset::Generated {
+            if configuration.polygonal_board {
+                ui.add(egui::Slider::new(&mut configuration.corners, 4..=360).text("Corners"));
+            }
+            let step = configuration.corners as f64; // Arbitrary numbers for now, just making sure division is easy
+            let minimum = configuration.corners * 1;
+            let maximum = (MAX_SQUARES / configuration.corners).max(1) * configuration.corners;
+            ui.add(egui::Slider::new(&mut configuration.squares, minimum..=maximum).text("Squares").step_by(step));
+        }
+        ui.add(egui::Slider::new(&mut configuration.bid_seconds, 1.0..=60.0).text("Bid Timer"));
+        ui.add(egui::Slider::new(&mut configuration.grace_seconds, 0.0..=600.0).text("Reconnect Grace"));
+        ui.add(egui::Slider::new(&mut configuration.turn_seconds, 0.0..=600.0).text("Turn Timer (0 is off)"));
+        ui.horizontal(|row| {
+            row.label("Timeout");
+            row.selectable_value(&mut configuration.timeout, Timeout::EndTurn, "End Turn");
+            row.selectable_value(&mut configuration.timeout, Timeout::Bot, "Bot");
+        });
+        ui.add(egui::Slider::new(&mut configuration.starting_money, 0..=10000).text("Starting Money"));
+        ui.add(egui::Slider::new(&mut configuration.go_salary, 0..=1000).text("GO Salary"));
+        ui.add(egui::Slider::new(&mut configuration.turn_limit, 1..=1000).text("Turn Limit"));
+        ui.horizontal(|row| {
+            row.label("Seed");
+            row.text_edit_singleline(&mut configuration.seed);
+        });
+        if configuration.seed().is_err() {
+            ui.colored_label(egui::Color32::RED, "Seed must be a whole number, games use a random one until it is");
+        }
+        ui.horizontal(|row| {
+            row.label("Scoring");
+            row.selectable_value(&mut configuration.scoring, Scoring::Winner, "Winner");
+            row.selectable_value(&mut configuration.scoring, Scoring::Ranked, "Ranked");
+            row.selectable_value(&mut configuration.scoring, Scoring::Share, "Share");
+            row.selectable_value(&mut configuration.scoring, Scoring::Stalemate, "Stalemate");
+        });
+    });
+}
 
-        ui.label("Players");
-        ui.separator();
+pub fn auto_start(
+    config: Res<Config>,
 
-        for (_player, name) in players.name.iter() {
-            ui.horizontal(|row| {
-                row.label(name);
-            });
+    mut rooms: ResMut<Rooms>,
+    mut configuration: ResMut<BoardConfiguration>
+) {
+    for (index, room) in rooms.list.iter_mut().enumerate() {
+        if room.restore.is_some() { // goes as soon as everyone is back, whatever the auto start settings
+            let connected = room.players.list.len() == room.players.name.len();
+            if room.state == GameState::Menu && connected && room.waiting_on().is_empty() {
+                info!("Everyone rejoined room {}, restoring", index);
+                room.state = GameState::Starting;
+            }
+            continue;
         }
-        ui.spacing();
 
-        ui.separator();
+        let players = &room.players;
+        if !room.auto_start || room.state != GameState::Menu { continue; }
+        if players.list.len() < configuration.start_players || players.list.len() != players.name.len() { continue; }
 
-        if !players.list.is_empty() && players.list.len() == players.name.len() && ui.button("Start").clicked() {
-            game_state.set(GameState::InGame);
+        room.auto_start = false;
+        match load_board(&mut configuration) {
+            Ok(()) => {
+                info!("Starting room {} with {} players", index, players.list.len());
+                room.state = GameState::Starting;
+            }
+            Err(error) if config.headless => panic!("Could not start: {}", error), // nobody can fix it from a menu
+            Err(error) => {
+                error!("{}", error);
+                configuration.board_error = Some(error.to_string());
+            }
         }
-    });
+    }
+}
+
+impl BoardConfiguration {
+    pub fn seed(&self) -> Result<Option<u64>, std::num::ParseIntError> {
+        let seed = self.seed.trim();
+        if seed.is_empty() { return Ok(None); }
+        seed.parse().map(Some)
+    }
+}
+
+// presets always make a square board, their tile count replaces the sliders
+fn load_board(configuration: &mut BoardConfiguration) -> Result<(), BoardError> {
+    configuration.definition = None;
+    configuration.board_error = None;
+
+    let definition = match configuration.preset {
+        BoardPreset::Classic => BoardDefinition::classic(),
+        BoardPreset::Generated => return Ok(()),
+        BoardPreset::File => BoardDefinition::load(configuration.board_file.trim())?
+    };
+    info!("Loaded board {} with {} tiles", definition.name, definition.tiles.len());
+
+    configuration.polygonal_board = false;
+    configuration.corners = 4;
+    configuration.squares = definition.tiles.len() as i32;
+    configuration.definition = Some(definition);
+    Ok(())
 }
\ No newline at end of file
@@ -1,202 +1,636 @@
 use bevy::prelude::*;
-use rand::Rng;
-use monai_store::{transfer::{Forfeit, PlayerActionChannel, BuyOwnable, SellOwnable, EndTurn, BeginTurn, BoardUpdateChannel, IssueReward, Ready, EndGame}, tile::{Chance, Tile, Corner, Tier, ServerSide}, player::{Money, Position, Action}};
+use monai_rules::{self as rules, replay::{Log, Record, Reward, End}};
+use monai_store::{transfer::{Forfeit, PlayerActionChannel, BuyOwnable, SellOwnable, AlterOwnable, MortgageOwnable, UnmortgageOwnable, EndTurn, DeclareBankruptcy, PlacedBid, PassBid, RollDice, PayBail, UseJailCard, BeginTurn, BoardUpdateChannel, IssueReward, Ready, EndGame, DrawCard, AuctionUpdate, EndAuction, TurnTimer, ProposeTrade, AcceptTrade, RejectTrade, CounterTrade, TradeOffered, TradeClosed, TradeOffer, ActionRejected}, tile::{Tile, Tier, ServerSide, FreeParking, Railroad, Utility}, player::{Money, Position, Action}};
 use naia_bevy_server::{events::MessageEvents, Server, UserKey};
-use crate::{state::{Players, Tiles, GameState}, menu::BoardConfiguration};
+use crate::{state::{Rooms, Room, Tiles, GameState, TurnClock, broadcast, record}, menu::BoardConfiguration, config::{WIN_REWARD, LOSS_REWARD}};
 
 pub fn message_receive(
-    mut players: ResMut<Players>,
+    mut rooms: ResMut<Rooms>,
 
     mut event_reader: EventReader<MessageEvents>,
-    mut event_writer: EventWriter<NextTurn>,
-    mut tiles: Query<(Entity, &mut Tile, Option<&Corner>, Option<&Chance>, &ServerSide), (Without<Money>, Without<Position>)>,
-    mut tokens: Query<(Entity, &mut Money, &Position), (Without<Tile>, Without<Corner>, Without<Chance>)>//,
+    mut turn_writer: EventWriter<NextTurn>,
+    mut game_writer: EventWriter<GameEvent>,
 
-    // mut commands: Commands
+    mut server: Server
 ) {
     for events in event_reader.iter() {
-        for (key, _message) in events.read::<PlayerActionChannel, Forfeit>() {
-            let (_, mut money, _) = tokens.get_mut(*players.current_player_entity()).expect("Unable to find forfeiting player");
-            *money.worth = -1;
+        let mut actions: Vec<(UserKey, rules::Action)> = vec![];
 
-            players.next_player();
-            event_writer.send(NextTurn(Some(key)))
+        for (key, _message) in events.read::<PlayerActionChannel, Forfeit>() {
+            actions.push((key, rules::Action::Forfeit));
         }
 
         for (key, _message) in events.read::<PlayerActionChannel, BuyOwnable>() {
-            let (player_token, mut money, position) = tokens.get_mut(players.list[&key]).expect("Could not find player from key on buy");
-            let (_entity, mut tile, _, _, _) = tiles.get_mut(Entity::from_bits(*position.tile)).expect("Player is not on a space");
-
-            *money.worth -= *tile.cost;
-            *tile.owner = Some(player_token.to_bits());
-            *tile.tier = Tier::Owned;
+            actions.push((key, rules::Action::Buy));
         }
 
         for (key, message) in events.read::<PlayerActionChannel, SellOwnable>() {
-            let (_, mut money, _) = tokens.get_mut(players.list[&key]).expect("Could not find player from key on sell");
-            let (_, mut tile, _, _, _) = tiles.get_mut(Entity::from_bits(message.id)).expect("Player tried to sell unavailable space");
+            if let Some(tile) = playing(&rooms, &key).and_then(|room| room.tiles.list.iter().position(|entity| entity.to_bits() == message.id)) {
+                actions.push((key, rules::Action::Sell(tile)));
+            } else {
+                reject(&mut server, &key, rules::Rejection::UnknownTile.to_string());
+            }
+        }
 
-            *money.worth += (*tile.cost as f32 * 0.8).ceil() as i32; // arbitrary
-            *tile.owner = None;
-            *tile.tier = Tier::None;
+        for (key, message) in events.read::<PlayerActionChannel, AlterOwnable>() {
+            if let Some(tile) = playing(&rooms, &key).and_then(|room| room.tiles.list.iter().position(|entity| entity.to_bits() == message.id)) {
+                actions.push((key, if message.build { rules::Action::Build(tile) } else { rules::Action::SellHouse(tile) }));
+            } else {
+                reject(&mut server, &key, rules::Rejection::UnknownTile.to_string());
+            }
+        }
+
+        for (key, message) in events.read::<PlayerActionChannel, MortgageOwnable>() {
+            if let Some(tile) = playing(&rooms, &key).and_then(|room| room.tiles.list.iter().position(|entity| entity.to_bits() == message.id)) {
+                actions.push((key, rules::Action::Mortgage(tile)));
+            } else {
+                reject(&mut server, &key, rules::Rejection::UnknownTile.to_string());
+            }
         }
 
-        // AlterOwnable
+        for (key, message) in events.read::<PlayerActionChannel, UnmortgageOwnable>() {
+            if let Some(tile) = playing(&rooms, &key).and_then(|room| room.tiles.list.iter().position(|entity| entity.to_bits() == message.id)) {
+                actions.push((key, rules::Action::Unmortgage(tile)));
+            } else {
+                reject(&mut server, &key, rules::Rejection::UnknownTile.to_string());
+            }
+        }
+
+        for (key, _message) in events.read::<PlayerActionChannel, RollDice>() {
+            actions.push((key, rules::Action::Roll));
+        }
+
+        for (key, _message) in events.read::<PlayerActionChannel, PayBail>() {
+            actions.push((key, rules::Action::PayBail));
+        }
+
+        for (key, _message) in events.read::<PlayerActionChannel, UseJailCard>() {
+            actions.push((key, rules::Action::UseJailCard));
+        }
 
         for (key, _message) in events.read::<PlayerActionChannel, EndTurn>() {
-            players.next_player();
-            event_writer.send(NextTurn(Some(key)));
+            actions.push((key, rules::Action::EndTurn));
         }
 
-        for _ in events.read::<PlayerActionChannel, Ready>() {
+        for (key, _message) in events.read::<PlayerActionChannel, DeclareBankruptcy>() {
+            actions.push((key, rules::Action::DeclareBankruptcy));
+        }
+
+        for (key, message) in events.read::<PlayerActionChannel, PlacedBid>() {
+            actions.push((key, rules::Action::Bid(message.amount)));
+        }
+
+        for (key, _message) in events.read::<PlayerActionChannel, PassBid>() {
+            actions.push((key, rules::Action::PassBid));
+        }
+
+        for (key, message) in events.read::<PlayerActionChannel, ProposeTrade>() {
+            let room = match playing(&rooms, &key) {
+                Some(room) => room,
+                None => {
+                    reject(&mut server, &key, rules::Rejection::UnknownPlayer.to_string());
+                    continue;
+                }
+            };
+
+            match (room.players.seat_of(message.to), offer(&room.tiles, &message.give), offer(&room.tiles, &message.take)) {
+                (Some(to), Some(give), Some(take)) => actions.push((key, rules::Action::ProposeTrade { to, give, take })),
+                _ => reject(&mut server, &key, "trade names an unknown player or space".to_string())
+            }
+        }
+
+        for (key, message) in events.read::<PlayerActionChannel, AcceptTrade>() {
+            actions.push((key, rules::Action::AcceptTrade(message.id as usize)));
+        }
+
+        for (key, message) in events.read::<PlayerActionChannel, RejectTrade>() {
+            actions.push((key, rules::Action::RejectTrade(message.id as usize)));
+        }
+
+        for (key, message) in events.read::<PlayerActionChannel, CounterTrade>() {
+            let room = match playing(&rooms, &key) {
+                Some(room) => room,
+                None => {
+                    reject(&mut server, &key, rules::Rejection::UnknownPlayer.to_string());
+                    continue;
+                }
+            };
+
+            match (offer(&room.tiles, &message.give), offer(&room.tiles, &message.take)) {
+                (Some(give), Some(take)) => actions.push((key, rules::Action::CounterTrade { trade: message.id as usize, give, take })),
+                _ => reject(&mut server, &key, "trade names an unknown space".to_string())
+            }
+        }
+
+        for (key, action) in actions {
+            let index = match rooms.of(&key) {
+                Some(index) if rooms.list[index].state == GameState::InGame => index,
+                _ => {
+                    reject(&mut server, &key, rules::Rejection::UnknownPlayer.to_string());
+                    continue;
+                }
+            };
+            let room = &mut rooms.list[index];
+
+            let seat = match room.players.seat(&key) {
+                Some(seat) => seat,
+                None => {
+                    reject(&mut server, &key, rules::Rejection::UnknownPlayer.to_string());
+                    continue;
+                }
+            };
+
+            // turn actions must have been offered, bids and trades are checked by the rules alone
+            if let Some(required) = offer_of(&action) {
+                if !room.players.offered.get(&key).is_some_and(|offered| offered.contains(&required)) {
+                    reject(&mut server, &key, format!("{:?} was not offered", action));
+                    continue;
+                }
+            }
+
+            if let Err(rejection) = apply_action(room, index, seat, action, &mut game_writer, &mut turn_writer) {
+                reject(&mut server, &key, rejection.to_string());
+            }
+        }
+
+        for (key, _) in events.read::<PlayerActionChannel, Ready>() {
+            let index = match rooms.of(&key) {
+                Some(index) if rooms.list[index].state == GameState::InGame => index,
+                _ => continue
+            };
+
+            let players = &mut rooms.list[index].players;
+            if !players.list.contains_key(&key) { continue; } // restored games can start with bankrupt players watching
+            if players.begun { continue; } // rejoined mid-game
             players.ready += 1;
             if players.ready == players.list.len() {
-                event_writer.send(NextTurn(None));
+                turn_writer.send(NextTurn(index));
                 players.ready = 0;
+                players.begun = true;
             }
         }
     }
 }
 
-pub struct NextTurn(pub Option<UserKey>);
-pub struct AwardPlayer(pub Entity, pub i32);
-pub struct BankruptPlayer(pub UserKey);
+// the room a player's messages are about, only while its game is running
+fn playing<'a>(rooms: &'a Rooms, key: &UserKey) -> Option<&'a Room> {
+    rooms.of(key).map(|index| &rooms.list[index]).filter(|room| room.state == GameState::InGame)
+}
 
-pub fn next_turn(
-    players: ResMut<Players>,
-    mut spaces: ResMut<Tiles>,
-    configuration: Res<BoardConfiguration>,
-    mut game_state: ResMut<NextState<GameState>>,
+pub struct NextTurn(pub usize); // room index
+pub struct GameEvent(pub usize, pub rules::Event);
+
+pub fn apply_action(
+    room: &mut Room,
+    index: usize,
+    seat: usize,
+    action: rules::Action,
+    game_writer: &mut EventWriter<GameEvent>,
+    turn_writer: &mut EventWriter<NextTurn>
+) -> Result<(), rules::Rejection> {
+    let acting = seat == room.game.current; // raising funds is always the current player too
+    let result = room.apply(seat, action)?;
+    game_writer.send_batch(result.into_iter().map(|event| GameEvent(index, event)));
+
+    // the current player is prompted after each of their own actions. Anyone else's only prompts them when it changed
+    // what they're offered, a trade completing a group or an auction handing the turn on, so the bot never sees a
+    // prompt mid-turn it didn't ask for
+    let (game, players) = (&room.game, &room.players);
+    let prompted = players.seats[game.current].and_then(|key| players.offered.get(&key));
+    let changed = prompted != Some(&offers(game, game.current));
+    if players.begun && !game.finished && game.auction.is_none() && (acting || changed) { turn_writer.send(NextTurn(index)); }
+    Ok(())
+}
 
-    mut event_reader: EventReader<NextTurn>,
-    mut award_writer: EventWriter<AwardPlayer>,
-    mut bankrupt_writer: EventWriter<BankruptPlayer>,
+// seats restored bankrupt have nobody to send to, the log still gets it
+fn reward(server: &mut Server, log: &mut Option<Log>, key: Option<UserKey>, seat: usize, reward: f32) {
+    if let Some(key) = key {
+        server.send_message::<BoardUpdateChannel, IssueReward>(&key, &IssueReward { reward });
+    }
+    record(log, &Record::Reward(Reward { seat, reward }));
+}
+
+fn reject(server: &mut Server, key: &UserKey, reason: String) {
+    warn!("Rejected action: {}", reason);
+    server.send_message::<BoardUpdateChannel, ActionRejected>(key, &ActionRejected { reason });
+}
 
-    tiles: Query<(Entity, &Transform, &Tile, Option<&Corner>, Option<&Chance>, &ServerSide), (Without<Money>, Without<Position>)>,
-    mut tokens: Query<(Entity, &mut Transform, &mut Money, &mut Position), (Without<Tile>, Without<Corner>, Without<Chance>)>,
+// What a seat is prompted with, still mono actions only, the client picks the tile
+fn offers(game: &rules::GameState, seat: usize) -> Vec<Action> {
+    let mut action_space: Vec<Action> = vec![];
+    for action in game.available_actions(seat) {
+        if let Some(offered) = offer_of(&action) {
+            if !action_space.contains(&offered) { action_space.push(offered); }
+        }
+    }
+    action_space
+}
 
-    mut server: Server
+// The prompt a rules action falls under, None for actions that are never offered
+fn offer_of(action: &rules::Action) -> Option<Action> {
+    match action {
+        rules::Action::Buy => Some(Action::Purchase),
+        rules::Action::Sell(_) => Some(Action::Sell),
+        rules::Action::Build(_) | rules::Action::SellHouse(_) => Some(Action::Alter),
+        rules::Action::Mortgage(_) | rules::Action::Unmortgage(_) => Some(Action::Mortgage),
+        rules::Action::Roll => Some(Action::Roll),
+        rules::Action::PayBail => Some(Action::PayBail),
+        rules::Action::UseJailCard => Some(Action::UseJailCard),
+        rules::Action::EndTurn => Some(Action::None),
+        rules::Action::DeclareBankruptcy => Some(Action::DeclareBankruptcy),
+        _ => None
+    }
+}
+
+fn offer(spaces: &Tiles, offer: &TradeOffer) -> Option<rules::trade::Offer> { // None if any tile is unknown
+    let tiles = offer.tiles.iter()
+        .map(|id| spaces.list.iter().position(|entity| entity.to_bits() == *id))
+        .collect::<Option<Vec<usize>>>()?;
+
+    Some(rules::trade::Offer { money: offer.money, tiles, jail_cards: offer.jail_cards })
+}
+
+fn trade_offer(spaces: &Tiles, offer: &rules::trade::Offer) -> TradeOffer {
+    TradeOffer {
+        money: offer.money,
+        tiles: offer.tiles.iter().map(|tile| spaces.list[*tile].to_bits()).collect(),
+        jail_cards: offer.jail_cards
+    }
+}
+
+// seats held past their grace period forfeit, and nobody is held once the game is over
+pub fn away_timer(
+    time: Res<Time>,
+    mut rooms: ResMut<Rooms>,
+
+    mut turn_writer: EventWriter<NextTurn>,
+    mut game_writer: EventWriter<GameEvent>,
+
+    mut commands: Commands
 ) {
-    for NextTurn(last_player) in event_reader.iter() {
-        if let Some(key) = last_player {
-            let (entity, _, money, _) = tokens.get(players.list[key]).expect("Last player is missing");
-
-            if *money.worth < 0 {
-                bankrupt_writer.send(BankruptPlayer(*key));
-
-                if players.list.len() - 1 == 1 {
-                    server.send_message::<BoardUpdateChannel, IssueReward>(
-                        players.list.keys().last().expect("No last player"), &IssueReward { reward: 1000.0 });
-                    server.broadcast_message::<BoardUpdateChannel, EndGame>(&EndGame);
-                    game_state.set(if configuration.auto_reset { GameState::AutoReset } else { GameState::Menu });
-                    return;
-                }
-            } else { // eventually we should split rewards into two parts, pre-turn and post-turn
-                let mut net_worth = *money.worth;
-                let mut sum_other_worths = 0;
+    for (index, room) in rooms.list.iter_mut().enumerate() {
+        if room.players.away.is_empty() { continue; }
 
-                tiles.iter().for_each(|(_, _, relinquish_tile, _, _, server_side)| {
-                    if *relinquish_tile.owner == Some(entity.to_bits()) {
-                        net_worth += ((1.5 + *server_side.probability) * *relinquish_tile.cost as f32).ceil() as i32;
-                    } else {
-                        sum_other_worths += ((1.5 + *server_side.probability) * *relinquish_tile.cost as f32).ceil() as i32;
-                    }
-                });
+        let expired: Vec<UserKey> = if room.state == GameState::InGame {
+            room.players.away.iter_mut().filter_map(|(key, timer)| timer.tick(time.delta()).finished().then_some(*key)).collect()
+        } else {
+            room.players.away.keys().copied().collect()
+        };
 
-                tokens.iter().for_each(|(other_entity, _, money, _)| {
-                    if other_entity == entity { return; }
-                    sum_other_worths += *money.worth;
-                });
+        for key in expired {
+            let seat = room.players.seat(&key);
+            let name = room.players.remove(&key, &mut commands).unwrap_or_default();
+            info!("{} didn't rejoin room {} in time", name, index);
 
-                server.send_message::<BoardUpdateChannel, IssueReward>(
-                    key, &IssueReward { reward: (net_worth as f32) / sum_other_worths as f32});
+            if let (Some(seat), GameState::InGame) = (seat, room.state) {
+                if let Err(rejection) = apply_action(room, index, seat, rules::Action::Forfeit, &mut game_writer, &mut turn_writer) {
+                    warn!("Could not forfeit for seat {} in room {}: {}", seat, index, rejection);
+                }
             }
         }
-        
-        if spaces.total_turns >= 100 { // stalemate
-            server.broadcast_message::<BoardUpdateChannel, EndGame>(&EndGame);
-            game_state.set(if configuration.auto_reset { GameState::AutoReset } else { GameState::Menu });
-            spaces.total_turns = 0;
-            return;
-        } else {
-            spaces.total_turns += 1;
-        }
+    }
+}
+
+const TURN_WARNING: f32 = 10.0; // seconds left when the player is warned, or half the turn if that's shorter
+const TIMEOUT_ACTIONS: usize = 100; // a policy always ends the turn well before this, it's only a backstop
 
-        let (token, _, mut money, mut position) = tokens.get_mut(*players.current_player_entity()).expect("Current player could not be found between turns");
+// a turn that runs out is played by the timeout policy until it passes, auctions are left to their own timer
+pub fn turn_timer(
+    time: Res<Time>,
+    configuration: Res<BoardConfiguration>,
+    mut rooms: ResMut<Rooms>,
 
-        {
-            let mut property = spaces.list.iter().position(|entity| entity.to_bits() == *position.tile).expect("Couldn't find current position of player in Vec form");
-            let mut random = rand::thread_rng();
+    mut turn_writer: EventWriter<NextTurn>,
+    mut game_writer: EventWriter<GameEvent>,
 
-            let roll = random.gen_range(2..=12) as usize;
-            property += roll;
-            if property >= spaces.list.len() {
-                property %= spaces.list.len();
-                *money.worth += 200;
+    mut server: Server
+) {
+    if configuration.turn_seconds <= 0.0 { return; }
+
+    for (index, room) in rooms.list.iter_mut().enumerate() {
+        if room.state != GameState::InGame || !room.players.begun || room.game.finished || room.game.auction.is_some() { continue; }
+        let seat = room.game.current;
+        let key = room.players.seats[seat].expect("Current player has no seat");
+
+        let turn = Some((room.game.turns, seat)); // the turn count stays put for a roll again after doubles
+        if room.clock.turn != turn {
+            room.clock = TurnClock { timer: Timer::from_seconds(configuration.turn_seconds, TimerMode::Once), turn, warned: false };
+            server.send_message::<BoardUpdateChannel, TurnTimer>(&key, &TurnTimer { seconds: configuration.turn_seconds, warning: false });
+            continue;
+        }
+
+        let clock = &mut room.clock;
+        clock.timer.tick(time.delta());
+        if !clock.warned && clock.timer.remaining_secs() <= TURN_WARNING.min(configuration.turn_seconds / 2.0) {
+            clock.warned = true;
+            server.send_message::<BoardUpdateChannel, TurnTimer>(&key, &TurnTimer { seconds: clock.timer.remaining_secs(), warning: true });
+        }
+        if !clock.timer.finished() { continue; }
+        if clock.timer.just_finished() {
+            info!("Seat {} in room {} ran out of time, playing {:?}", seat, index, configuration.timeout);
+        }
+
+        // stops wherever the board takes over, another roll for doubles comes back through here
+        for _ in 0..TIMEOUT_ACTIONS {
+            let action = match configuration.timeout.choose(&room.game, seat) {
+                Some(action) => action,
+                None => break
+            };
+            if let Err(rejection) = apply_action(room, index, seat, action, &mut game_writer, &mut turn_writer) {
+                warn!("Timeout policy was rejected for seat {} in room {}: {}", seat, index, rejection);
+                break;
             }
 
-            *position.tile = spaces.list[property].to_bits();
+            let game = &room.game;
+            if game.finished || game.current != seat || game.auction.is_some() || (!game.rolled && game.players[seat].jailed.is_none()) { break; }
+        }
+    }
+}
+
+pub fn auction_timer(
+    time: Res<Time>,
+    mut rooms: ResMut<Rooms>,
+
+    mut turn_writer: EventWriter<NextTurn>,
+    mut game_writer: EventWriter<GameEvent>
+) {
+    for (index, room) in rooms.list.iter_mut().enumerate() {
+        if room.state != GameState::InGame { continue; }
+
+        let waiting: Vec<usize> = match &room.game.auction {
+            Some(auction) => auction.bidders.iter().copied().filter(|bidder| Some(*bidder) != auction.leader).collect(),
+            None => continue
+        };
+
+        if !room.timer.0.tick(time.delta()).just_finished() { continue; }
+
+        for bidder in waiting { // anyone who has not outbid the leader in time passes
+            if let Err(rejection) = apply_action(room, index, bidder, rules::Action::PassBid, &mut game_writer, &mut turn_writer) {
+                warn!("Could not pass for seat {} in room {}: {}", bidder, index, rejection);
+            }
         }
+    }
+}
+
+pub fn next_turn(
+    mut rooms: ResMut<Rooms>,
+    configuration: Res<BoardConfiguration>,
+
+    mut event_reader: EventReader<NextTurn>,
+    mut game_writer: EventWriter<GameEvent>,
 
-        let (_, _, tile, corner, chance, _) = tiles.get(Entity::from_bits(*position.tile)).expect("Current player is sitting on an unknown tile");
-        
-        // TEMPORARY COST SPACE CODE
-        if *tile.owner != None && *tile.owner != Some(token.to_bits()) {
-            award_writer.send(AwardPlayer(Entity::from_bits(tile.owner.unwrap()), *tile.cost)); // arbitrarily changing to not / 10
-            *money.worth -= *tile.cost; // arbitrarily changing to not / 10 to avoid stalemate
+    mut server: Server
+) {
+    // one prompt per room with the latest state, several actions in a frame each ask for one
+    let mut prompts: Vec<usize> = event_reader.iter().map(|NextTurn(index)| *index).collect();
+    prompts.sort_unstable();
+    prompts.dedup();
+
+    for index in prompts.iter() {
+        let room = &mut rooms.list[*index];
+        if room.state != GameState::InGame || room.game.finished || room.game.auction.is_some() { continue; } // asked for before a later action ended the game or started an auction
+        if !room.game.rolled && !configuration.snapshot_dir.is_empty() { room.save(&configuration.snapshot_dir, false); } // turns start here, before the roll
+        let current = room.game.current;
+        if !room.game.rolled && room.game.players[current].jailed.is_none() { // jailed players choose how to leave first
+            let result = room.apply(current, rules::Action::Roll).expect("Current player could not roll between turns");
+            game_writer.send_batch(result.into_iter().map(|event| GameEvent(*index, event)));
         }
-        // END TEMPORARY COST SPACE
+        let (game, players) = (&room.game, &mut room.players);
 
-        let mut action_space: Vec<Action> = vec![];
-        // TEMPORARY ACTION SPACE CODE, MONO ACTIONS ONLY
-        action_space.push(Action::None);
-        if !tiles.iter().filter(|x| *x.2.owner == Some(token.to_bits()))
-            .collect::<Vec<(Entity, &Transform, &Tile, Option<&Corner>, Option<&Chance>, &ServerSide)>>().is_empty() { action_space.push(Action::Sell); }
-        if *money.worth >= 0 && *tile.tier == Tier::None && corner.is_none() && chance.is_none() { action_space.push(Action::Purchase); }
-        // END TEMPORARY ACTION SPACE
+        let action_space = offers(game, current);
+        players.offered.clear(); // only the current player is ever prompted
+        let key = players.seats[current].expect("Current player has no seat");
+        players.offered.insert(key, action_space.clone());
 
         let new_turn = BeginTurn {
             available_actions: action_space
         };
 
-        server.send_message::<BoardUpdateChannel, BeginTurn>(players.current_player_key(), &new_turn);
+        server.send_message::<BoardUpdateChannel, BeginTurn>(&key, &new_turn);
     }
 }
 
-pub fn reward_player(
-    mut event_reader: EventReader<AwardPlayer>,
-    mut money_query: Query<&mut Money>,
+pub fn game_event(
+    mut rooms: ResMut<Rooms>,
+    configuration: Res<BoardConfiguration>,
+
+    mut event_reader: EventReader<GameEvent>,
+
+    tiles: Query<&ServerSide>,
+
+    mut server: Server,
+    mut commands: Commands
 ) {
-    for AwardPlayer(entity, amount) in event_reader.iter() {
-        *money_query.get_mut(*entity).expect("Attempted to award non-existant entity").worth += amount;
+    let mut auctions_changed: Vec<usize> = vec![];
+
+    for GameEvent(index, event) in event_reader.iter() {
+        let room = &mut rooms.list[*index];
+        let (game, players, spaces, log) = (&room.game, &mut room.players, &room.tiles, &mut room.log);
+
+        match *event {
+            rules::Event::TurnEnded { player } if !game.players[player].bankrupt => {
+                let key = players.seats[player];
+
+                // eventually we should split rewards into two parts, pre-turn and post-turn
+                let mut net_worth = game.players[player].money;
+                let mut sum_other_worths = 0;
+
+                for (index, tile) in game.tiles.iter().enumerate() {
+                    let probability = *tiles.get(spaces.list[index]).expect("Tile is missing server side").probability;
+                    let worth = ((1.5 + probability) * tile.cost as f32).ceil() as i32;
+
+                    if tile.owner == Some(player) {
+                        net_worth += worth;
+                    } else {
+                        sum_other_worths += worth;
+                    }
+                }
+
+                for (other, state) in game.players.iter().enumerate() {
+                    if other == player || state.bankrupt { continue; }
+                    sum_other_worths += state.money;
+                }
+
+                reward(&mut server, log, key, player, (net_worth as f32) / sum_other_worths as f32);
+            }
+            rules::Event::AuctionStarted { .. } => {
+                room.timer.0 = Timer::from_seconds(configuration.bid_seconds, TimerMode::Once);
+                auctions_changed.push(*index);
+            }
+            rules::Event::BidPlaced { .. } => {
+                room.timer.0.reset();
+                auctions_changed.push(*index);
+            }
+            rules::Event::BidPassed { .. } => auctions_changed.push(*index),
+            rules::Event::AuctionEnded { tile, winner, price } => {
+                broadcast(&mut server, players, &EndAuction {
+                    tile: spaces.list[tile].to_bits(),
+                    winner: winner.and_then(|seat| players.entity(seat)).map(|entity| entity.to_bits()),
+                    price
+                });
+            }
+            rules::Event::TradeProposed { trade, from, to } => {
+                // a trade accepted in the same frame is already gone, TradeClosed covers it
+                if let (Some(pending), Some(sender), Some(receiver)) = (game.trades.iter().find(|x| x.id == trade), players.entity(from), players.entity(to)) {
+                    let offered = TradeOffered {
+                        id: trade as u64,
+                        from: sender.to_bits(),
+                        to: receiver.to_bits(),
+                        give: trade_offer(&spaces, &pending.give),
+                        take: trade_offer(&spaces, &pending.take)
+                    };
+
+                    for key in [players.seats[from], players.seats[to]].iter().flatten() {
+                        server.send_message::<BoardUpdateChannel, TradeOffered>(key, &offered);
+                    }
+                }
+            }
+            rules::Event::TradeAccepted { trade, from, to } | rules::Event::TradeRejected { trade, from, to } => {
+                let closed = TradeClosed { id: trade as u64, accepted: matches!(event, rules::Event::TradeAccepted { .. }) };
+                for key in [players.seats[from], players.seats[to]].iter().flatten() {
+                    server.send_message::<BoardUpdateChannel, TradeClosed>(key, &closed);
+                }
+            }
+            rules::Event::DrewCard { player, deck, card } => {
+                if let Some(entity) = players.entity(player) {
+                    broadcast(&mut server, players, &DrawCard { player: entity.to_bits(), deck, card });
+                }
+            }
+            rules::Event::Bankrupt { player, .. } => {
+                let key = players.seats[player];
+                if let Some(key) = key {
+                    players.offered.remove(&key);
+                    if let Some(entity) = players.list.remove(&key) {
+                        commands.get_entity(entity).expect("Non existant player on channel").despawn_recursive();
+                    }
+                    if players.name.contains_key(&key) { // gone for good if they never rejoined
+                        players.bankrupt.push(key); // keeps its name, they get a seat again on reset
+                    }
+                }
+
+                reward(&mut server, log, key, player, LOSS_REWARD);
+            }
+            rules::Event::TurnLimit { ref standings } => {
+                info!("Room {} hit the turn limit, standings {:?}", index, standings);
+                for (seat, amount) in configuration.scoring.rewards(standings) {
+                    reward(&mut server, log, players.seats[seat], seat, amount);
+                }
+            }
+            rules::Event::GameOver { winner } => {
+                if let Some(winner) = winner {
+                    reward(&mut server, log, players.seats[winner], winner, WIN_REWARD);
+                }
+                record(log, &Record::End(End { winner, turns: game.turns, standings: game.standings() }));
+                *log = None; // closes the file
+
+                broadcast(&mut server, players, &EndGame);
+                room.state = if configuration.auto_reset { GameState::AutoReset } else { GameState::Menu };
+            }
+            _ => {}
+        }
+    }
+
+    // only the latest auction state matters, several bids can land in one frame and rooms' events interleave
+    auctions_changed.sort_unstable();
+    auctions_changed.dedup();
+    for index in auctions_changed {
+        let room = &rooms.list[index];
+        let (game, players, spaces) = (&room.game, &room.players, &room.tiles);
+
+        if let Some(auction) = &game.auction {
+            let tile = spaces.list[auction.tile].to_bits();
+            let leader = auction.leader.and_then(|seat| players.entity(seat)).map(|entity| entity.to_bits());
+
+            for (seat, key) in players.seats.iter().enumerate() {
+                let key = match key {
+                    Some(key) if !game.players[seat].bankrupt => key,
+                    _ => continue
+                };
+                server.send_message::<BoardUpdateChannel, AuctionUpdate>(key, &AuctionUpdate {
+                    tile,
+                    highest: auction.highest,
+                    leader,
+                    bidding: auction.bidders.contains(&seat)
+                });
+            }
+        }
     }
 }
 
-pub fn bankrupt_player(
-    mut players: ResMut<Players>,
+pub fn sync_state( // mirror the rules state into replicated components
+    rooms: Res<Rooms>,
 
-    mut event_reader: EventReader<BankruptPlayer>,
+    mut tiles: Query<&mut Tile, (Without<Money>, Without<Position>)>,
+    mut tokens: Query<(&mut Money, &mut Position), Without<Tile>>,
+    mut parking: Query<&mut FreeParking>,
+    mut railroads: Query<&mut Railroad>,
+    mut utilities: Query<&mut Utility>
+) {
+    if !rooms.is_changed() { return; }
 
-    mut tiles: Query<(Entity, &mut Tile, Option<&Corner>, Option<&Chance>, &ServerSide), (Without<Money>, Without<Position>)>,
+    for room in rooms.list.iter().filter(|room| room.state == GameState::InGame) {
+        sync_room(room, &mut tiles, &mut tokens, &mut parking, &mut railroads, &mut utilities);
+    }
+}
 
-    mut server: Server,
-    mut commands: Commands,
+fn sync_room(
+    room: &Room,
+
+    tiles: &mut Query<&mut Tile, (Without<Money>, Without<Position>)>,
+    tokens: &mut Query<(&mut Money, &mut Position), Without<Tile>>,
+    parking: &mut Query<&mut FreeParking>,
+    railroads: &mut Query<&mut Railroad>,
+    utilities: &mut Query<&mut Utility>
 ) {
-    for BankruptPlayer(key) in event_reader.iter() {
-        let entity = players.list.remove(&key).expect("Non existant player on channel");
-        let entity_bits = entity.to_bits();
-        players.name.remove(&key);
+    let (game, players, spaces) = (&room.game, &room.players, &room.tiles);
+
+    for (index, state) in game.tiles.iter().enumerate() {
+        if let Ok(mut tile) = tiles.get_mut(spaces.list[index]) {
+            let owner = state.owner
+                .and_then(|seat| players.entity(seat))
+                .map(|entity| entity.to_bits());
+            let tier = tier(state.tier);
+
+            if *tile.owner != owner { *tile.owner = owner; }
+            if *tile.tier != tier { *tile.tier = tier; }
+            if *tile.houses != state.houses as u8 { *tile.houses = state.houses as u8; }
+            if *tile.mortgaged != state.mortgaged { *tile.mortgaged = state.mortgaged; }
+        }
+
+        let owned = state.owner.map_or(0, |owner| game.count_owned(owner, state.kind));
+        if let Ok(mut railroad) = railroads.get_mut(spaces.list[index]) {
+            if *railroad.owned != owned { *railroad.owned = owned; }
+        }
+        if let Ok(mut utility) = utilities.get_mut(spaces.list[index]) {
+            if *utility.owned != owned { *utility.owned = owned; }
+        }
+    }
 
-        tiles.iter_mut().for_each(|(_, mut relinquish_tile, _, _, _)| {
-            if *relinquish_tile.owner == Some(entity_bits) {
-                *relinquish_tile.owner = None;
-                *relinquish_tile.tier = Tier::None;
+    if game.settings.jackpot {
+        for entity in spaces.list.iter() {
+            if let Ok(mut free_parking) = parking.get_mut(*entity) {
+                if *free_parking.jackpot != Some(game.jackpot) { *free_parking.jackpot = Some(game.jackpot); }
             }
-        });
+        }
+    }
+
+    for (seat, state) in game.players.iter().enumerate() {
+        if let Some(Ok((mut money, mut position))) = players.entity(seat).map(|entity| tokens.get_mut(*entity)) {
+            let tile = spaces.list[state.position].to_bits();
 
-        players.bankrupt.push(*key);
-        
-        commands.get_entity(entity).expect("Non existant player on channel").despawn_recursive();
-        server.send_message::<BoardUpdateChannel, IssueReward>(key, &IssueReward { reward: -1000.0 });
+            if *money.worth != state.money { *money.worth = state.money; }
+            if *position.tile != tile { *position.tile = tile; }
+            if *position.jailed != state.jailed.is_some() { *position.jailed = state.jailed.is_some(); }
+            if *position.jail_cards != state.jail_cards.len() { *position.jail_cards = state.jail_cards.len(); }
+        }
     }
-}
\ No newline at end of file
+}
+
+pub fn tier(tier: rules::tile::Tier) -> Tier {
+    match tier {
+        rules::tile::Tier::None => Tier::None,
+        rules::tile::Tier::Owned => Tier::Owned,
+        rules::tile::Tier::House => Tier::House,
+        rules::tile::Tier::Hotel => Tier::Hotel
+    }
+}
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
-use monai_store::{player::Position, tile::Tile};
+use monai_store::{player::Position, tile::{Tile, Title}};
+use crate::{state::Rooms, viewer::Viewer};
 
 
 pub fn render_position(
@@ -16,20 +17,38 @@ pub fn render_position(
     }
 }
 
-pub fn render_owner(
+pub fn render_owner( // unowned tiles go back to the color their board file gave them
     players: Query<&Handle<ColorMaterial>, Without<Tile>>,
-    tiles: Query<(&Handle<ColorMaterial>, &Tile), Without<Position>>,
+    tiles: Query<(&Handle<ColorMaterial>, &Tile, Option<&Title>), Without<Position>>,
 
     mut materials: ResMut<Assets<ColorMaterial>>
 ) {
-    for (sprite, tile)  in tiles.iter() {
+    for (sprite, tile, title)  in tiles.iter() {
         if let Some(owner) = *tile.owner {
             if let Ok(player) = players.get(Entity::from_bits(owner)) {
                 let player_color = materials.get(player).expect("Handle dangling").color;
                 materials.get_mut(sprite).expect("Handle dangling").color = player_color;
             }
         } else {
-            materials.get_mut(sprite).expect("Handle dangling").color = Color::hex("#1e1e2e").expect("Hex should be valid");
+            let color = title.and_then(|title| title.color.as_deref()).unwrap_or("#1e1e2e");
+            materials.get_mut(sprite).expect("Handle dangling").color = Color::hex(color).expect("Hex should be valid");
+        }
+    }
+}
+
+pub fn render_room( // every room draws in the same place, so only the shown one is visible, and none while a replay is open
+    rooms: Res<Rooms>,
+    viewer: Res<Viewer>,
+
+    mut visibilities: Query<&mut Visibility>
+) {
+    for (index, room) in rooms.list.iter().enumerate() {
+        let visibility = if index == rooms.shown && viewer.playback.is_none() { Visibility::Inherited } else { Visibility::Hidden };
+
+        for entity in room.tiles.list.iter().chain(room.players.list.values()) {
+            if let Ok(mut current) = visibilities.get_mut(*entity) {
+                if *current != visibility { *current = visibility; }
+            }
         }
     }
 }
\ No newline at end of file
@@ -1,27 +1,80 @@
 use bevy::prelude::*;
-use std::collections::HashMap;
 use naia_bevy_server::{Server, events::{AuthEvents, ConnectEvent, TickEvent, DisconnectEvent}, transport::webrtc, CommandsExt};
-use monai_store::{Auth, transfer::{SendPlayer, BoardUpdateChannel}};
-use crate::{state::{Players, Code, Tiles}, menu::BoardConfiguration};
+use monai_store::{Auth, transfer::{SendPlayer, StartGame, BoardUpdateChannel}};
+use monai_rules::snapshot::Snapshot;
+use crate::{state::{Rooms, GameState}, menu::{BoardConfiguration, BoardPreset}, config::Config, message::NextTurn};
 
 pub fn initialize_server(
+    config: Res<Config>,
+
     mut commands: Commands,
     mut server: Server
 ) {
-    let address = webrtc::ServerAddrs::new( // Σ ascii("monai") = 1096
-        "127.0.0.1:1095".parse().expect("Could not parse signal local address"),
-        "127.0.0.1:1096".parse().expect("Could not parse WebRTC local address"),
-        "http://127.0.0.1:1096"
+    let address = webrtc::ServerAddrs::new(
+        config.bind.parse().expect("Signal address was checked with the config"),
+        config.webrtc_bind.parse().expect("WebRTC address was checked with the config"),
+        &config.public_url
     );
 
     let socket = webrtc::Socket::new(&address, server.socket_config());
     server.listen(socket);
+
+    let (preset, board_file) = match config.board.as_str() {
+        "classic" => (BoardPreset::Classic, String::new()),
+        "generated" => (BoardPreset::Generated, String::new()),
+        file => (BoardPreset::File, file.to_string())
+    };
     
-    // Make this random later
-    commands.insert_resource(Players { list: HashMap::new(), current: None, name: HashMap::new(), ready: 0, bankrupt: vec![], finish: 0 });
-    commands.insert_resource(Code { value: "MONAI".to_string(), game_room: server.make_room().key() });
-    commands.insert_resource(BoardConfiguration { polygonal_board: false, corners: 4, squares: 40, auto_reset: false });
-    commands.insert_resource(Tiles { list: vec![], tested_probability: vec![], groups: vec![], total_turns: 0 });
+    // a fixed code is numbered per room when there are several
+    let mut rooms = Rooms::default();
+    for index in 0..config.rooms {
+        let code = match &config.code {
+            Some(code) if config.rooms == 1 => code.clone(),
+            Some(code) => format!("{}{}", code, index + 1),
+            None => rooms.unique_code()
+        };
+        rooms.add(code, config.players.is_some() || config.headless, &mut server);
+    }
+
+    // snapshots take over the first rooms, with more added if there aren't enough
+    for (index, path) in config.restore.iter().enumerate() {
+        let snapshot = Snapshot::load(path).unwrap_or_else(|error| panic!("Could not restore {}, it loaded with the config: {}", path, error));
+        let index = if index < rooms.list.len() {
+            index
+        } else {
+            let code = rooms.unique_code();
+            rooms.add(code, false, &mut server)
+        };
+        rooms.restore(index, snapshot);
+    }
+    commands.insert_resource(rooms);
+    // headless boards run back to back games and wait for a full table, the bots are built for four
+    commands.insert_resource(BoardConfiguration {
+        polygonal_board: false,
+        corners: 4,
+        squares: 40,
+        auto_reset: config.auto_reset || config.headless,
+        bid_seconds: 10.0,
+        grace_seconds: config.grace,
+        turn_seconds: config.turn_seconds,
+        timeout: config.timeout,
+        starting_money: config.starting_money,
+        go_salary: config.go_salary,
+        turn_limit: config.turn_limit,
+        scoring: config.scoring,
+        seed: config.seed.map(|seed| seed.to_string()).unwrap_or_default(),
+        log_dir: config.log_dir.clone(),
+        snapshot_dir: config.snapshot_dir.clone(),
+        snapshot_file: String::new(),
+        snapshot_error: None,
+        jackpot: false,
+        preset,
+        board_file,
+        definition: None,
+        board_error: None,
+        landing: Default::default(),
+        start_players: config.players.unwrap_or(4)
+    });
     
     info!("Naia server initialized");
 }
@@ -34,7 +87,7 @@ pub fn tick( // Usually game logic would be here. We're not using tick-buffered
     let ticked = event_reader.is_empty();
 
     if ticked {
-        for (_, key, entity) in server.scope_checks() { // Ignore room key, we only have one
+        for (_, key, entity) in server.scope_checks() { // Ignore room key, naia only pairs users with entities in their own room
             server.user_scope(&key).include(&entity); // Include all entities, no checking
         }
     }
@@ -43,28 +96,44 @@ pub fn tick( // Usually game logic would be here. We're not using tick-buffered
 pub fn authorize_player(
     mut event_reader: EventReader<AuthEvents>,
 
-    code: Res<Code>,
-    mut players: ResMut<Players>,
+    mut rooms: ResMut<Rooms>,
 
     mut server: Server
 ) {
     for event in event_reader.iter() {
         for (key, auth) in event.read::<Auth>() {
-            if players.name.contains_key(&key) {
+            if rooms.users.contains_key(&key) {
                 server.reject_connection(&key);
                 info!("Declined connection from player {}, key already authorized", auth.name);
                 continue;
             }
 
-            if auth.code != code.value  {
-                server.reject_connection(&key);
-                info!("Declined connection from player {}, code invalid", auth.name);
-                continue;
+            let index = match rooms.find(&auth.code) {
+                Some(index) => index,
+                None => {
+                    server.reject_connection(&key);
+                    info!("Declined connection from player {}, code invalid", auth.name);
+                    continue;
+                }
+            };
+
+            // a restored game only takes back the players it had
+            let room = &rooms.list[index];
+            if let Some(snapshot) = &room.restore {
+                let seats = snapshot.header.players.iter().zip(&snapshot.game.players)
+                    .filter(|(name, state)| **name == auth.name && !state.bankrupt).count();
+                let taken = room.players.name.values().filter(|name| **name == auth.name).count();
+                if taken >= seats {
+                    server.reject_connection(&key);
+                    info!("Declined connection from player {}, no seat left under that name in the restored game", auth.name);
+                    continue;
+                }
             }
 
             server.accept_connection(&key);
-            info!("Authorized connection from player {}", auth.name);
-            players.name.insert(key, auth.name);
+            info!("Authorized connection from player {} into room {}", auth.name, index);
+            rooms.users.insert(key, index);
+            rooms.list[index].players.name.insert(key, auth.name);
         }
     }
 }
@@ -72,23 +141,45 @@ pub fn authorize_player(
 pub fn connect_player(
     mut event_reader: EventReader<ConnectEvent>,
 
-    code: Res<Code>,
-    mut players: ResMut<Players>,
+    mut rooms: ResMut<Rooms>,
+    mut turn_writer: EventWriter<NextTurn>,
 
     mut commands: Commands,
     mut server: Server
 ) {
     for ConnectEvent(key) in event_reader.iter() { // needs player components
-        let user = server.user_mut(key).enter_room(&code.game_room).address();
+        let index = match rooms.of(key) {
+            Some(index) => index,
+            None => continue
+        };
+        let room = &mut rooms.list[index];
+
+        let user = server.user_mut(key).enter_room(&room.code.game_room).address();
+
+        // the same name as a held seat is that player back, they keep their entity and pick up where they were
+        if let Some(old) = room.players.away_named(&room.players.name[key]) {
+            let players = &mut room.players;
+            players.reclaim(old, *key);
+            info!("{} rejoined their seat, {}", players.name[key], user);
+
+            if let Some(entity) = players.list.get(key) {
+                server.send_message::<BoardUpdateChannel, SendPlayer>(key, &SendPlayer { id: entity.to_bits() });
+            }
+            server.send_message::<BoardUpdateChannel, StartGame>(key, &StartGame { seed: room.game.settings.seed });
+            if players.seat(key) == Some(room.game.current) && room.game.auction.is_none() {
+                turn_writer.send(NextTurn(index)); // the last prompt went to the old connection
+            }
+            continue;
+        }
         let entity = commands
             .spawn_empty()
             .enable_replication(&mut server)
             .id();
 
-        server.room_mut(&code.game_room).add_entity(&entity);
-        players.list.insert(*key, entity);
+        server.room_mut(&room.code.game_room).add_entity(&entity);
+        room.players.list.insert(*key, entity);
 
-        info!("Connected and spawned entity for {}, {}", players.name[key], user);
+        info!("Connected and spawned entity for {}, {}", room.players.name[key], user);
 
         server.send_message::<BoardUpdateChannel, SendPlayer>(key, &SendPlayer { id: entity.to_bits() })
     }
@@ -97,15 +188,28 @@ pub fn connect_player(
 pub fn disconnect_player(
     mut event_reader: EventReader<DisconnectEvent>,
 
-    mut players: ResMut<Players>,
+    mut rooms: ResMut<Rooms>,
+    configuration: Res<BoardConfiguration>,
 
     mut commands: Commands
 ) {
     for DisconnectEvent(key, _user) in event_reader.iter() {
-        let entity = players.list.remove(key).expect("User has not been registered");
-        let name = players.name.remove(key).expect("User has no name");
-        commands.get_entity(entity).expect("User entity already removed").despawn_recursive();
+        let room = match rooms.users.remove(key) {
+            Some(index) => &mut rooms.list[index],
+            None => continue // never made it past authorization
+        };
+        let (game, players) = (&room.game, &mut room.players);
+
+        // a seat still in the game is held for the same name to come back, play waits on them meanwhile
+        let seated = room.state == GameState::InGame && players.seat(key).map_or(false, |seat| !game.players[seat].bankrupt);
+        if seated {
+            players.away.insert(*key, Timer::from_seconds(configuration.grace_seconds, TimerMode::Once));
+            info!("{} disconnected, holding their seat for {} seconds", players.name[key], configuration.grace_seconds);
+            continue;
+        }
 
-        info!("Disconnected and removed entity for {}", name);
+        // bankrupt players and anyone between games have no entity or seat to keep
+        let name = players.remove(key, &mut commands).unwrap_or_default();
+        info!("Disconnected and removed {}", name);
     }
-}
\ No newline at end of file
+}
@@ -1,104 +1,312 @@
 use bevy::prelude::*;
-use monai_store::transfer::{PlayerActionChannel, Finish};
-use std::collections::HashMap;
+use monai_rules::{self as rules, GameState as Rules, replay::{Log, Record, Step, Header}, snapshot::{Snapshot, LogPosition, VERSION}};
+use monai_store::{transfer::{PlayerActionChannel, BoardUpdateChannel, Finish}, player::Action};
+use naia_bevy_shared::Message;
+use std::{collections::HashMap, path::Path, time::{SystemTime, UNIX_EPOCH}};
+use rand::seq::SliceRandom;
 use naia_bevy_server::{UserKey, RoomKey, events::MessageEvents, Server};
 
-#[derive(Resource)]
 pub struct Code {
     pub value: String,
     pub game_room: RoomKey
 }
 
+impl Code {
+    // alternating consonants and vowels so it can be read out loud, no letters that look alike
+    pub fn generate() -> String {
+        const CONSONANTS: &[u8] = b"BDFGHJKLMNPRSTVZ";
+        const VOWELS: &[u8] = b"AEU";
+        let mut random = rand::thread_rng();
+
+        (0..6).map(|index| {
+            let letters = if index % 2 == 0 { CONSONANTS } else { VOWELS };
+            *letters.choose(&mut random).expect("Letters are not empty") as char
+        }).collect()
+    }
+
+    pub fn matches(&self, code: &str) -> bool {
+        code.trim().eq_ignore_ascii_case(&self.value)
+    }
+}
+
+// Every game on the server, each with its own naia room
+#[derive(Resource, Default)]
+pub struct Rooms {
+    pub list: Vec<Room>, // index is the room's id in events
+    pub users: HashMap<UserKey, usize>, // the room each authorized user was routed to
+    pub shown: usize // drawn in the window
+}
+
+pub struct Room {
+    pub code: Code,
+    pub state: GameState,
+    pub players: Players,
+    pub tiles: Tiles,
+    pub game: Game,
+    pub timer: AuctionTimer,
+    pub clock: TurnClock,
+    pub log: Option<Log>, // the game being played, None between games or when logging is off
+    pub header: Option<Header>, // how the running game started, snapshots carry it
+    pub corners: usize,
+    pub games: usize, // started in this room so far, a fixed seed is varied by it
+    pub restore: Option<Snapshot>, // starts in place of a new game once every seat's name has rejoined
+    pub auto_start: bool // waiting on BoardConfiguration.start_players for the first game
+}
+
+impl Rooms {
+    pub fn add(&mut self, code: String, auto_start: bool, server: &mut Server) -> usize {
+        info!("Room {} join code is {}", self.list.len(), code);
+        self.list.push(Room {
+            code: Code { value: code, game_room: server.make_room().key() },
+            state: GameState::Menu,
+            players: Players::default(),
+            tiles: Tiles::default(),
+            game: Game::default(),
+            timer: AuctionTimer::default(),
+            clock: TurnClock::default(),
+            log: None,
+            header: None,
+            corners: 4,
+            games: 0,
+            restore: None,
+            auto_start
+        });
+
+        self.list.len() - 1
+    }
+
+    // players rejoin with the snapshot's code, unless another room took it since
+    pub fn restore(&mut self, index: usize, snapshot: Snapshot) {
+        if self.find(&snapshot.code).map_or(true, |found| found == index) {
+            self.list[index].code.value = snapshot.code.clone();
+        } else {
+            warn!("Room code {} is taken, the restored game uses {}", snapshot.code, self.list[index].code.value);
+        }
+
+        let (room, turns) = (&mut self.list[index], snapshot.game.turns);
+        room.auto_start = false;
+        room.restore = Some(snapshot);
+        info!("Room {} restores turn {}, waiting for {:?} to rejoin", index, turns, room.waiting_on());
+    }
+
+    pub fn unique_code(&self) -> String {
+        loop {
+            let code = Code::generate();
+            if !self.list.iter().any(|room| room.code.matches(&code)) { return code; }
+        }
+    }
+
+    pub fn find(&self, code: &str) -> Option<usize> {
+        self.list.iter().position(|room| room.code.matches(code))
+    }
+
+    pub fn of(&self, key: &UserKey) -> Option<usize> {
+        self.users.get(key).copied()
+    }
+}
+
+impl Room {
+    // every change to the game goes through here so it's logged
+    pub fn apply(&mut self, seat: usize, action: rules::Action) -> Result<Vec<rules::Event>, rules::Rejection> {
+        let events = self.game.apply(seat, action.clone())?;
+        record(&mut self.log, &Record::Action(Step { seat, action, events: events.clone() }));
+        Ok(events)
+    }
+
+    // names from the snapshot being restored that nobody connected has yet
+    pub fn waiting_on(&self) -> Vec<&str> {
+        let mut waiting: Vec<&str> = match &self.restore {
+            Some(snapshot) => snapshot.header.players.iter().zip(&snapshot.game.players)
+                .filter(|(_, state)| !state.bankrupt) // out of the game already, nobody holds the restore for them
+                .map(|(name, _)| name.as_str()).collect(),
+            None => return vec![]
+        };
+
+        for key in self.players.list.keys() {
+            if let Some(seat) = self.players.name.get(key).and_then(|name| waiting.iter().position(|waiting| *waiting == name.as_str())) {
+                waiting.remove(seat);
+            }
+        }
+        waiting
+    }
+
+    // automatic ones overwrite {code}.json every turn, ones asked for are kept under the time they were taken
+    pub fn save(&self, directory: &str, kept: bool) {
+        let header = match (&self.header, self.state) {
+            (Some(header), GameState::InGame) => header.clone(),
+            _ => return
+        };
+
+        let snapshot = Snapshot {
+            version: VERSION,
+            code: self.code.value.clone(),
+            corners: self.corners,
+            header,
+            game: self.game.0.clone(),
+            log: self.log.as_ref().map(|log| LogPosition { path: log.path().to_path_buf(), length: log.length() })
+        };
+
+        let name = if kept {
+            let taken = SystemTime::now().duration_since(UNIX_EPOCH).expect("Clock is before 1970").as_millis();
+            format!("{}-{}.json", self.code.value, taken)
+        } else {
+            format!("{}.json", self.code.value)
+        };
+        let path = Path::new(directory).join(name);
+
+        match snapshot.save(&path) {
+            Ok(()) if kept => info!("Saved room {} to {}", self.code.value, path.display()),
+            Ok(()) => {}
+            Err(error) => warn!("Could not save snapshot {}: {}", path.display(), error)
+        }
+    }
+}
+
+// a log that can't be written to is dropped, the game carries on without it
+pub fn record(log: &mut Option<Log>, record: &Record) {
+    if let Some(writer) = log {
+        if let Err(error) = writer.write(record) {
+            warn!("Could not write to the game log, it stops here: {}", error);
+            *log = None;
+        }
+    }
+}
+
 // These structs are an abomination of ECS, but it works for now
-#[derive(Resource)]
+#[derive(Default)]
 pub struct Players {
     pub list: HashMap<UserKey, Entity>,
-    pub current: Option<UserKey>,
+    pub seats: Vec<Option<UserKey>>, // index is the player's index in the rules state, None for seats restored bankrupt
     pub name: HashMap<UserKey, String>,
+    pub offered: HashMap<UserKey, Vec<Action>>, // from the last BeginTurn, anything else is rejected
     pub bankrupt: Vec<UserKey>,
+    pub away: HashMap<UserKey, Timer>, // disconnected mid-game, their seat, entity and name wait until this runs out
     pub ready: usize,
+    pub begun: bool, // everyone was ready and the first turn went out, Ready from a rejoining client doesn't count
     pub finish: usize,
 }
 
-#[derive(Resource)]
+#[derive(Default)]
 pub struct Tiles {
     pub list: Vec<Entity>,
-    pub tested_probability: Vec<i32>,
+    pub probability: Vec<f32>, // steady-state landing chance, see monai_rules::landing
     pub groups: Vec<Vec<Entity>>,
-    pub total_turns: usize
+    pub decks: Vec<Entity>
 }
 
-#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+#[derive(Default, Deref, DerefMut)]
+pub struct Game(pub Rules);
+
+#[derive(Default)]
+pub struct AuctionTimer(pub Timer); // passes for everyone still bidding once it runs out
+
+#[derive(Default)]
+pub struct TurnClock { // plays the rest of a turn once it runs out
+    pub timer: Timer,
+    pub turn: Option<(usize, usize)>, // the turn count and seat it's running for, rolling again after doubles keeps it going
+    pub warned: bool
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
 pub enum GameState {
     #[default]
     Menu,
+    Starting, // the board is generated on the next update
     InGame,
     AutoReset
 }
 
-impl Players { // we might not **need** to deref here
-    pub fn _current_player(&self) -> (&UserKey, &Entity) {
-        let player = self.list.get_key_value(&self.current.unwrap()).unwrap();
-        return (player.0, player.1);
+impl Players {
+    pub fn seat(&self, key: &UserKey) -> Option<usize> {
+        self.seats.iter().position(|seat| seat.as_ref() == Some(key))
     }
 
-    pub fn current_player_entity(&self) -> &Entity {
-        return self.list.get(&self.current.unwrap()).unwrap();
+    pub fn entity(&self, seat: usize) -> Option<&Entity> {
+        self.seats[seat].and_then(|key| self.list.get(&key))
     }
 
-    pub fn current_player_key(&self) -> &UserKey {
-        return self.list.get_key_value(&self.current.unwrap()).unwrap().0;
+    pub fn seat_of(&self, id: u64) -> Option<usize> { // from the id clients see
+        (0..self.seats.len()).find(|seat| self.entity(*seat).map(|entity| entity.to_bits()) == Some(id))
     }
-    
-    pub fn next_player(&mut self) {
-        let counter: Vec<UserKey> = self.list.keys().cloned().collect();
 
-        let mut current_position = counter.iter().position(|&key| key == self.current.unwrap()).unwrap();
-        let total_size = counter.len();
+    pub fn everyone(&self) -> impl Iterator<Item = &UserKey> { // still connected, bankrupt or not
+        self.list.keys().chain(self.bankrupt.iter())
+    }
 
-        current_position += 1;
-        current_position %= total_size;
+    pub fn away_named(&self, name: &str) -> Option<UserKey> {
+        self.away.keys().find(|key| self.name.get(key).map_or(false, |away| away == name)).copied()
+    }
 
-        self.current = Some(counter[current_position]);
+    // a rejoining client takes over everything the old connection held, its name is already set
+    pub fn reclaim(&mut self, old: UserKey, new: UserKey) {
+        self.away.remove(&old);
+        self.name.remove(&old);
+
+        for key in self.seats.iter_mut().flatten().chain(self.bankrupt.iter_mut()).filter(|key| **key == old) {
+            *key = new;
+        }
+        if let Some(entity) = self.list.remove(&old) {
+            self.list.insert(new, entity);
+        }
+        if let Some(offered) = self.offered.remove(&old) {
+            self.offered.insert(new, offered);
+        }
     }
 
-    pub fn initial_player(&mut self) { // Assumes 'list' has been populated
-        let counter: Vec<UserKey> = self.list.keys().cloned().collect();
+    // gone for good, whatever seat they had stays with the old key
+    pub fn remove(&mut self, key: &UserKey, commands: &mut Commands) -> Option<String> {
+        if let Some(entity) = self.list.remove(key) {
+            commands.get_entity(entity).expect("User entity already removed").despawn_recursive();
+        }
+        self.away.remove(key);
+        self.offered.remove(key);
+        self.bankrupt.retain(|bankrupt| bankrupt != key);
+        self.name.remove(key)
+    }
+}
 
-        self.current = Some(counter[0]);
+// messages only go to the room they're about
+pub fn broadcast<M: Message>(server: &mut Server, players: &Players, message: &M) {
+    for key in players.everyone() {
+        server.send_message::<BoardUpdateChannel, M>(key, message);
     }
 }
 
 pub fn auto_reset( // just funnel into next game
-    mut game_state: ResMut<NextState<GameState>>,
-    mut players: ResMut<Players>,
-    mut spaces: ResMut<Tiles>,
-    code: Res<Code>,
+    mut rooms: ResMut<Rooms>,
 
     mut event_reader: EventReader<MessageEvents>,
 
     mut commands: Commands,
     mut server: Server,
 ) {
-    while let Some(last_player) = players.list.keys().last() { // wont run after first iteration
-        let last_player = *last_player;
-        commands.get_entity(players.list.remove(&last_player)
-            .expect("Last player is present without entity")).expect("Last player entity is not found").despawn_recursive();
+    for room in rooms.list.iter_mut().filter(|room| room.state == GameState::AutoReset) {
+        let players = &mut room.players;
+        while let Some(last_player) = players.list.keys().last() { // wont run after first iteration
+            let last_player = *last_player;
+            commands.get_entity(players.list.remove(&last_player)
+                .expect("Last player is present without entity")).expect("Last player entity is not found").despawn_recursive();
 
-        players.bankrupt.push(last_player);
+            players.bankrupt.push(last_player);
+        }
     }
 
     for events in event_reader.iter() {
-        for _ in events.read::<PlayerActionChannel, Finish>() {
-            players.finish += 1;
-            info!("Player has finished despawning {}/{}", players.finish, players.bankrupt.len());
-            if players.finish == players.bankrupt.len() {
-                players.finish = 0;
-                info!("Resuming game, tile count {}", spaces.list.len());
-                crate::generator::reset_game(&mut spaces, &mut players, &code, &mut commands, &mut server);
-                info!("Generator finished, tile count {}", spaces.list.len());
-                game_state.set(GameState::InGame);
+        for (key, _) in events.read::<PlayerActionChannel, Finish>() {
+            let room = match rooms.of(&key).map(|index| &mut rooms.list[index]) {
+                Some(room) if room.state == GameState::AutoReset => room,
+                _ => continue
+            };
+
+            room.players.finish += 1;
+            info!("Player has finished despawning {}/{}", room.players.finish, room.players.bankrupt.len());
+            if room.players.finish == room.players.bankrupt.len() {
+                room.players.finish = 0;
+                info!("Resuming game, tile count {}", room.tiles.list.len());
+                crate::generator::reset_game(&mut room.tiles, &mut room.players, &room.code, &mut commands, &mut server);
+                info!("Generator finished, tile count {}", room.tiles.list.len());
+                room.state = GameState::Starting;
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use monai_rules::{GameState as Rules, replay::{Replay, LogError}, tile::TileKind};
+use monai_store::{player::Position, tile::{Tile, Tier, Title}};
+use crate::{generator, message::tier};
+
+// Plays a game log back on local entities, nothing here is replicated to clients
+#[derive(Resource, Default)]
+pub struct Viewer {
+    pub path: String,
+    pub playback: Option<Playback>,
+    pub error: Option<String>
+}
+
+pub struct Playback {
+    pub replay: Replay,
+    pub states: Vec<Rules>, // the game after each step, filled in as far as playback has reached
+    pub step: usize,
+    pub playing: bool,
+    pub speed: f32, // steps per second
+    pub elapsed: f32, // since the last step while playing
+    pub tiles: Vec<Entity>,
+    pub tokens: Vec<Entity> // by seat
+}
+
+impl Playback {
+    // rebuilds any states up to the step that haven't been yet, so stepping back is free
+    fn seek(&mut self, step: usize) -> Result<(), LogError> {
+        let step = step.min(self.replay.steps.len());
+        while self.states.len() <= step {
+            let mut state = self.states.last().expect("Playback starts with a state").clone();
+            self.replay.apply(&mut state, self.states.len() - 1)?;
+            self.states.push(state);
+        }
+
+        self.step = step;
+        Ok(())
+    }
+
+    fn state(&self) -> &Rules {
+        &self.states[self.step]
+    }
+
+    fn name(&self, seat: usize) -> &str {
+        self.replay.header.players.get(seat).map_or("?", |name| name.as_str())
+    }
+}
+
+pub fn controls(
+    mut viewer: ResMut<Viewer>,
+
+    mut contexts: EguiContexts,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands
+) {
+    let viewer = &mut *viewer; // split borrows between the playback and its error
+    egui::Window::new("Replay").show(contexts.ctx_mut(), |ui| {
+        if viewer.playback.is_none() {
+            ui.horizontal(|row| {
+                row.label("Log File");
+                row.text_edit_singleline(&mut viewer.path);
+            });
+            if ui.button("Open").clicked() {
+                match Replay::load(viewer.path.trim()) {
+                    Ok(replay) => {
+                        info!("Replaying {} with {} steps", viewer.path.trim(), replay.steps.len());
+                        viewer.playback = Some(open(replay, &mut meshes, &mut materials, &mut commands));
+                        viewer.error = None;
+                    }
+                    Err(error) => viewer.error = Some(error.to_string())
+                }
+            }
+            if let Some(error) = &viewer.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            return;
+        }
+
+        let playback = viewer.playback.as_mut().expect("Checked above");
+        let last = playback.replay.steps.len();
+        let mut target = playback.step;
+
+        let board = playback.replay.header.board.as_ref().map_or("Generated board", |board| board.name.as_str());
+        ui.label(format!("{}, seed {}", board, playback.replay.header.settings.seed));
+        ui.horizontal(|row| {
+            if row.button("|<").clicked() { target = 0; }
+            if row.button("<").clicked() { target = target.saturating_sub(1); }
+            if row.button(if playback.playing { "Pause" } else { "Play" }).clicked() {
+                playback.playing = !playback.playing;
+                playback.elapsed = 0.0;
+            }
+            if row.button(">").clicked() { target += 1; }
+            if row.button(">|").clicked() { target = last; }
+        });
+        ui.add(egui::Slider::new(&mut target, 0..=last).text("Step"));
+        ui.add(egui::Slider::new(&mut playback.speed, 0.5..=100.0).logarithmic(true).text("Steps per Second"));
+
+        if target != playback.step {
+            playback.playing = false;
+            if let Err(error) = playback.seek(target) {
+                viewer.error = Some(error.to_string());
+            }
+        }
+        if let Some(error) = &viewer.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        ui.separator();
+
+        let state = playback.state();
+        ui.label(format!("Turn {}, {} to play", state.turns, playback.name(state.current)));
+        for (seat, player) in state.players.iter().enumerate() {
+            let standing = if player.bankrupt { "bankrupt".to_string() } else { format!("${} on tile {}", player.money, player.position) };
+            ui.label(format!("{}: {}", playback.name(seat), standing));
+        }
+        ui.separator();
+
+        if playback.step > 0 {
+            let step = &playback.replay.steps[playback.step - 1];
+            ui.label(format!("{}: {:?}", playback.name(step.seat), step.action));
+            for event in step.events.iter() {
+                ui.label(format!("{:?}", event));
+            }
+        }
+        for (_, reward) in playback.replay.rewards.iter().filter(|(after, _)| *after == playback.step) {
+            ui.label(format!("{} rewarded {}", playback.name(reward.seat), reward.reward));
+        }
+        if playback.step == last {
+            match &playback.replay.end {
+                Some(end) => match end.winner {
+                    Some(winner) => ui.label(format!("{} won after {} turns", playback.name(winner), end.turns)),
+                    None => ui.label(format!("No winner after {} turns", end.turns))
+                },
+                None => ui.label("The log ends before the game did")
+            };
+        }
+
+        if ui.button("Close").clicked() {
+            for entity in playback.tiles.iter().chain(playback.tokens.iter()) {
+                commands.entity(*entity).despawn_recursive();
+            }
+            viewer.playback = None;
+            viewer.error = None;
+        }
+    });
+}
+
+// the log doesn't record the polygon, presets are always square and generated boards start every side with a corner
+fn open(
+    replay: Replay,
+
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    commands: &mut Commands
+) -> Playback {
+    let header = &replay.header;
+    let squares = header.tiles.len();
+    let corners = match &header.board {
+        Some(_) => 4,
+        None => header.tiles.iter()
+            .filter(|tile| matches!(tile.kind, TileKind::Go | TileKind::Jail | TileKind::FreeParking | TileKind::GoToJail | TileKind::Corner))
+            .count()
+    };
+    let corners = if corners > 0 && squares % corners == 0 { corners } else { squares.max(1) }; // one tile a side still places them all
+
+    let (scale, placements) = generator::placements(corners as i32, squares as i32);
+    let tiles: Vec<Entity> = placements.into_iter().enumerate().map(|(index, transform)| {
+        let color = header.board.as_ref()
+            .and_then(|board| board.tiles[index].color.as_deref())
+            .unwrap_or("#1e1e2e");
+
+        let mut entity_commands = commands.spawn((
+            generator::tile_bundle(scale, index % (squares / corners) == 0, color, transform, meshes, materials),
+            Tile::new(Tier::None, None, header.tiles[index].cost)
+        ));
+        if let Some(board) = &header.board { // render_owner puts the color back once a tile is sold
+            entity_commands.insert(Title::new(board.tiles[index].name.clone(), board.tiles[index].color.clone()));
+        }
+        entity_commands.id()
+    }).collect();
+
+    let tokens = (0..header.players.len()).map(|seat| {
+        commands.spawn((
+            generator::token_bundle(seat, meshes, materials),
+            Position::new(tiles[0].to_bits())
+        )).id()
+    }).collect();
+
+    let start = replay.start();
+    Playback { replay, states: vec![start], step: 0, playing: false, speed: 4.0, elapsed: 0.0, tiles, tokens }
+}
+
+pub fn playback(
+    time: Res<Time>,
+    mut viewer: ResMut<Viewer>
+) {
+    let viewer = &mut *viewer;
+    let playback = match viewer.playback.as_mut() {
+        Some(playback) if playback.playing => playback,
+        _ => return
+    };
+
+    playback.elapsed += time.delta_seconds();
+    while playback.elapsed >= 1.0 / playback.speed {
+        playback.elapsed -= 1.0 / playback.speed;
+
+        if playback.step >= playback.replay.steps.len() {
+            playback.playing = false;
+            break;
+        }
+        if let Err(error) = playback.seek(playback.step + 1) {
+            viewer.error = Some(error.to_string());
+            playback.playing = false;
+            break;
+        }
+    }
+}
+
+// mirrors the shown step into the same components the live board renders from
+pub fn render_playback(
+    viewer: Res<Viewer>,
+
+    mut tiles: Query<&mut Tile, Without<Position>>,
+    mut tokens: Query<(&mut Position, &mut Visibility), Without<Tile>>
+) {
+    if !viewer.is_changed() { return; }
+    let playback = match &viewer.playback {
+        Some(playback) => playback,
+        None => return
+    };
+    let state = playback.state();
+
+    for (index, tile_state) in state.tiles.iter().enumerate() {
+        if let Ok(mut tile) = tiles.get_mut(playback.tiles[index]) {
+            let owner = tile_state.owner.map(|seat| playback.tokens[seat].to_bits());
+            let tier = tier(tile_state.tier);
+
+            if *tile.owner != owner { *tile.owner = owner; }
+            if *tile.tier != tier { *tile.tier = tier; }
+            if *tile.houses != tile_state.houses as u8 { *tile.houses = tile_state.houses as u8; }
+            if *tile.mortgaged != tile_state.mortgaged { *tile.mortgaged = tile_state.mortgaged; }
+        }
+    }
+
+    for (seat, player) in state.players.iter().enumerate() {
+        if let Ok((mut position, mut visibility)) = tokens.get_mut(playback.tokens[seat]) {
+            let tile = playback.tiles[player.position].to_bits();
+            let shown = if player.bankrupt { Visibility::Hidden } else { Visibility::Inherited };
+
+            if *position.tile != tile { *position.tile = tile; }
+            if *position.jailed != player.jailed.is_some() { *position.jailed = player.jailed.is_some(); }
+            if *visibility != shown { *visibility = shown; }
+        }
+    }
+}
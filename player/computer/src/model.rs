@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 use dfdx::{optim::{Adam, AdamConfig}, prelude::{SplitInto, modules::Linear, ReLU, DeviceBuildExt, ZeroGrads, Module, huber_loss, Optimizer, SaveToNpz, LoadFromNpz}, tensor::{Cpu, TensorFrom, Trace}, tensor_ops::{SelectTo, Backward}};
-use monai_store::{transfer::{BeginTurn, BoardUpdateChannel, PlayerActionChannel, SendPlayer, EndTurn, BuyOwnable, SellOwnable, IssueReward, EndGame}, tile::{Tile, Corner, Chance, ServerSide}, player::{Money, Position, ServerPlayer, Action}};
+use monai_store::{transfer::{BeginTurn, BoardUpdateChannel, PlayerActionChannel, SendPlayer, EndTurn, BuyOwnable, SellOwnable, RollDice, UseJailCard, DeclareBankruptcy, IssueReward, EndGame, AuctionUpdate, EndAuction, PlacedBid, PassBid, TradeOffered, AcceptTrade, RejectTrade, ActionRejected}, tile::{Tile, Corner, Chance, ServerSide, Railroad, Utility}, player::{Money, Position, ServerPlayer, Action}};
 use naia_bevy_client::{events::MessageEvents, Client};
 use rand::{prelude::Distribution, seq::SliceRandom};
 use crate::{SQUARES, GameState, ClientResources};
@@ -13,6 +13,7 @@ const ACTION: usize = 3;
 const BATCH: usize = 32; // number of turns before learning, 30 is the average for a game
 const DISCOUNT: f32 = 0.9;
 const DECAY: f32 = 0.005;
+const BID_STEP: i32 = 10; // how much the bot raises the highest bid by
 
 type Device = Cpu;
 // type Device = Cuda;
@@ -51,6 +52,7 @@ pub struct StatefulInformation {
     pub optimizer: Adam<QModule, f32, Device>,
     pub epsilon: f32,
     pub experience: Vec<Transition>,
+    pub bids: Vec<Transition>, // held until the auction ends, the purchase head decides whether to raise
     pub steps: i32
 }
 
@@ -82,6 +84,7 @@ pub fn add_stateful(
         optimizer: optim, // We remove gradients since it annihilates the borrow checker
         epsilon: 0.3f32,
         experience: vec![],
+        bids: vec![],
         steps: 0
     });
 }
@@ -92,6 +95,7 @@ pub fn message_event( // action picker
 
     tiles: Query<(Entity, &mut Tile, Option<&Corner>, Option<&Chance>, &ServerSide), (Without<Money>, Without<Position>)>,
     tokens: Query<(Entity, &mut Money, &Position, &ServerPlayer), (Without<Tile>, Without<Corner>, Without<Chance>)>,
+    sets: Query<(&ServerSide, Option<&Railroad>, Option<&Utility>)>,
 
     mut event_reader: EventReader<MessageEvents>,
     mut game_state: ResMut<NextState<GameState>>,
@@ -99,16 +103,29 @@ pub fn message_event( // action picker
 ) {
     for events in event_reader.iter() {
         for turn in events.read::<BoardUpdateChannel, BeginTurn>() {
+            if turn.available_actions.contains(&Action::Roll) { // leaving jail, no head for this yet
+                if turn.available_actions.contains(&Action::UseJailCard) {
+                    client.send_message::<PlayerActionChannel, UseJailCard>(&UseJailCard);
+                } else {
+                    client.send_message::<PlayerActionChannel, RollDice>(&RollDice);
+                }
+                continue;
+            }
+
+            // raising funds reuses the heads, sell until solvent or do nothing and go bankrupt
+            let raising = turn.available_actions.contains(&Action::DeclareBankruptcy);
+
             // First see if we are exploring vs exploiting
-            let state = get_state(&tiles, &tokens, stateful.entity);
+            let state = get_state(&tiles, &tokens, &sets, stateful.entity);
             let action: (usize, usize);
             if stateful.epsilon > rand::random::<f32>() { // explore!
                 println!("Exploring, epsilon {}", stateful.epsilon);
-                let available = turn.available_actions.iter().map(|x| {
+                let available = turn.available_actions.iter().filter_map(|x| {
                     match x {
-                        Action::Purchase => 0,
-                        Action::Sell => 1,
-                        Action::None => 2
+                        Action::Purchase => Some(0),
+                        Action::Sell => Some(1),
+                        Action::None | Action::DeclareBankruptcy => Some(2),
+                        _ => None // model has no head for these yet
                     }
                 }).collect::<Vec<usize>>();
 
@@ -138,7 +155,8 @@ pub fn message_event( // action picker
                     match action {
                         Action::Purchase => action_type_mask[0] = 1.0,
                         Action::Sell => action_type_mask[1] = 1.0,
-                        Action::None => action_type_mask[2] = 1.0
+                        Action::None | Action::DeclareBankruptcy => action_type_mask[2] = 1.0,
+                        _ => {}
                     }
                 }
                 println!("Actions mask: {:?}", action_type_mask);
@@ -169,10 +187,15 @@ pub fn message_event( // action picker
                         .expect("Selling property not found");
 
                     client.send_message::<PlayerActionChannel, SellOwnable>(&SellOwnable { id: *server_side.id });
-                    client.send_message::<PlayerActionChannel, EndTurn>(&EndTurn);
+                    if !raising { client.send_message::<PlayerActionChannel, EndTurn>(&EndTurn); } // the board prompts again while in debt
 
                     println!("Sold property");
                 }
+                2 if raising => {
+                    client.send_message::<PlayerActionChannel, DeclareBankruptcy>(&DeclareBankruptcy);
+
+                    println!("Declared bankruptcy");
+                }
                 2 => {
                     client.send_message::<PlayerActionChannel, EndTurn>(&EndTurn);
 
@@ -184,12 +207,91 @@ pub fn message_event( // action picker
             stateful.experience.push((state, 0.0, action, None)); // Default case for the experience. When the server responds we will change .1 and .3, if necessary
         }
 
+        for auction in events.read::<BoardUpdateChannel, AuctionUpdate>() {
+            if !auction.bidding || auction.leader == Some(stateful.entity) { continue; }
+
+            let money = tokens.iter().find(|x| *x.3.id == stateful.entity).map(|x| *x.1.worth).unwrap_or(0);
+            let square = tiles.iter().find(|x| *x.4.id == auction.tile).map(|x| *x.4.index).unwrap_or(0);
+            let raise = auction.highest + BID_STEP;
+
+            let state = get_state(&tiles, &tokens, &sets, stateful.entity);
+            let bid = if raise > money {
+                false
+            } else if stateful.epsilon > rand::random::<f32>() {
+                rand::random::<bool>()
+            } else {
+                let (action_type, _) = stateful.model.forward(stateful.device.tensor(state));
+                let quality = action_type.as_vec();
+                quality[0] > quality[2] // purchase against doing nothing
+            };
+
+            if bid {
+                client.send_message::<PlayerActionChannel, PlacedBid>(&PlacedBid { amount: raise });
+                println!("Bid {}", raise);
+            } else {
+                client.send_message::<PlayerActionChannel, PassBid>(&PassBid);
+                println!("Passed on bidding");
+            }
+
+            stateful.bids.push((state, 0.0, (if bid { 0 } else { 2 }, square), None));
+        }
+
+        for ended in events.read::<BoardUpdateChannel, EndAuction>() {
+            println!("Auction ended at {} for {:?}", ended.price, ended.winner);
+            let next_state = get_state(&tiles, &tokens, &sets, stateful.entity);
+            let bids: Vec<Transition> = stateful.bids.drain(..).map(|mut x| { x.3 = Some(next_state); x }).collect();
+
+            // keep our own turn last, its reward has not arrived yet
+            let at = match stateful.experience.last() {
+                Some(transition) if transition.3.is_none() => stateful.experience.len() - 1,
+                _ => stateful.experience.len()
+            };
+            stateful.experience.splice(at..at, bids);
+        }
+
+        for offered in events.read::<BoardUpdateChannel, TradeOffered>() {
+            if offered.to != stateful.entity { continue; } // our own proposals, bots don't make any yet
+
+            // rewrite the state as if the trade went through, then see which one the model values more
+            let before = get_state(&tiles, &tokens, &sets, stateful.entity);
+            let mut after = before;
+            after[SQUARES] += (offered.give.money - offered.take.money) as f32; // our worth always comes first
+            for (_, tile, _, _, server_side) in &tiles {
+                let worth = (1.5 * *tile.cost as f32).ceil();
+                if offered.give.tiles.contains(&*server_side.id) {
+                    after[*server_side.index] = if *tile.mortgaged { 3.0 } else { 1.0 };
+                    after[SQUARES] += worth;
+                } else if offered.take.tiles.contains(&*server_side.id) {
+                    after[*server_side.index] = if *tile.mortgaged { 4.0 } else { 2.0 };
+                    after[SQUARES] -= worth;
+                }
+            }
+
+            let accept = if stateful.epsilon > rand::random::<f32>() {
+                rand::random::<bool>()
+            } else {
+                stateful.value(after) > stateful.value(before)
+            };
+
+            if accept {
+                client.send_message::<PlayerActionChannel, AcceptTrade>(&AcceptTrade { id: offered.id });
+                println!("Accepted trade {}", offered.id);
+            } else {
+                client.send_message::<PlayerActionChannel, RejectTrade>(&RejectTrade { id: offered.id });
+                println!("Rejected trade {}", offered.id);
+            }
+        }
+
+        for rejected in events.read::<BoardUpdateChannel, ActionRejected>() {
+            println!("Action rejected: {}", rejected.reason);
+        }
+
         for issued in events.read::<BoardUpdateChannel, IssueReward>() {
             let entity = stateful.entity;
             if let Some(transition) = stateful.experience.last_mut() {
                 println!("Received reward {}", issued.reward);
                 transition.1 = issued.reward; // +=
-                transition.3 = Some(get_state(&tiles, &tokens, entity)); // only on next turn?
+                transition.3 = Some(get_state(&tiles, &tokens, &sets, entity)); // only on next turn?
             }
 
             if stateful.experience.len() > BATCH {
@@ -212,6 +314,7 @@ pub fn message_event( // action picker
             println!("Saving model");
             stateful.model.save(format!("models/{}.npz", info.name)).expect("Couldn't save model to .npz");
             stateful.entity = 0;
+            stateful.bids.clear();
             game_state.set(GameState::Despawning);
         }
     }
@@ -220,6 +323,7 @@ pub fn message_event( // action picker
 pub fn get_state(
     tiles: &Query<(Entity, &mut Tile, Option<&Corner>, Option<&Chance>, &ServerSide), (Without<Money>, Without<Position>)>,
     tokens: &Query<(Entity, &mut Money, &Position, &ServerPlayer), (Without<Tile>, Without<Corner>, Without<Chance>)>,
+    sets: &Query<(&ServerSide, Option<&Railroad>, Option<&Utility>)>,
 
     owner: u64
 ) -> [f32; SQUARES + (PLAYERS * 2)] { // handle end of the game for just one player
@@ -260,24 +364,36 @@ pub fn get_state(
         }
     }
 
-    let mut squares = [0; SQUARES];
+    let mut squares = [0.0; SQUARES];
     for (_, tile, _, _, server_side) in tiles {
         if *tile.owner == Some(owner) {
-            squares[*server_side.index] = 1;
+            squares[*server_side.index] = if *tile.mortgaged { 3.0 } else { 1.0 };
         } else if tile.owner.is_some() {
-            squares[*server_side.index] = 2; // We don't own and can't buy the property
+            squares[*server_side.index] = if *tile.mortgaged { 4.0 } else { 2.0 }; // We don't own and can't buy the property, mortgaged charges no rent
+        }
+    }
+    // railroads and utilities charge more the more of them the owner has, a fraction keeps the ownership above readable
+    for (server_side, railroad, utility) in sets {
+        let owned = railroad.map(|x| *x.owned).or(utility.map(|x| *x.owned)).unwrap_or(0);
+        if owned > 1 {
+            squares[*server_side.index] += (owned - 1) as f32 / 4.0;
         }
     }
 
     let state: [f32; STATE] = 
-    squares.iter().chain(players.iter())
-        .map(|v| *v as f32).collect::<Vec<f32>>()
+    squares.iter().copied().chain(players.iter().map(|v| *v as f32))
+        .collect::<Vec<f32>>()
         .try_into().expect("Couldn't convert state");
 
     return state;
 }
 
 impl StatefulInformation {
+    pub fn value(&self, state: [f32; STATE]) -> f32 { // best action quality from a state
+        let (action_type, _) = self.model.forward(self.device.tensor(state));
+        action_type.as_vec().into_iter().fold(f32::MIN, f32::max)
+    }
+
     pub fn train(&mut self) {
         let mut rng = rand::thread_rng();
         let uniform = rand::distributions::Uniform::from(0..self.experience.len());
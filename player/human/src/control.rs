@@ -1,6 +1,6 @@
 use bevy::{prelude::*};
 use bevy_egui::{egui, EguiContexts};
-use monai_store::{Auth, transfer::{BoardUpdateChannel, BeginTurn, SendPlayer, StartGame, PlayerActionChannel, BuyOwnable, SellOwnable, Forfeit, EndTurn, EndGame, Ready}, player::{Action, Money, Position, ServerPlayer}, tile::{Tile, Chance, Corner, ServerSide}};
+use monai_store::{Auth, transfer::{BoardUpdateChannel, BeginTurn, SendPlayer, StartGame, TurnTimer, PlayerActionChannel, BuyOwnable, SellOwnable, AlterOwnable, MortgageOwnable, UnmortgageOwnable, Forfeit, EndTurn, RollDice, PayBail, UseJailCard, DeclareBankruptcy, EndGame, Ready, DrawCard, PlacedBid, PassBid, AuctionUpdate, EndAuction, ProposeTrade, AcceptTrade, RejectTrade, CounterTrade, TradeOffered, TradeClosed, TradeOffer, ActionRejected}, player::{Action, Money, Position, ServerPlayer}, tile::{Tile, Chance, Corner, ServerSide, Deck, Go, Tax, FreeParking, Jail, GoToJail, Title, Railroad, Utility}};
 use naia_bevy_client::{Client, transport::webrtc, events::MessageEvents};
 
 #[derive(Resource)]
@@ -11,17 +11,58 @@ pub struct StatefulInformation {
     pub url: String,
     pub can_buy: bool,
     pub can_sell: bool,
+    pub can_alter: bool,
+    pub can_mortgage: bool,
+    pub can_roll: bool,
+    pub can_pay_bail: bool,
+    pub can_use_card: bool,
     pub can_end: bool,
+    pub can_declare: bool, // raising funds, only selling is possible
     pub entity: u64,
     pub started: bool,
+    pub seed: Option<u64>, // from StartGame
+    pub turn_clock: Option<(f64, bool)>, // when the board plays our turn for us, in Time::elapsed_seconds_f64, and whether we were warned
     pub ready: bool,
+    pub last_card: String,
+    pub auction: Option<(u64, i32, Option<u64>, bool)>, // tile, highest bid, leader, whether we can still bid
+    pub bid: i32,
+    pub last_auction: String,
+    pub last_rejection: String,
+    pub trade_to: Option<u64>, // trade being edited
+    pub trade_give: TradeOffer,
+    pub trade_take: TradeOffer,
+    pub countering: Option<u64>,
+    pub trades: Vec<(u64, u64, u64, TradeOffer, TradeOffer)>, // pending id, from, to, give, take
+}
+
+impl StatefulInformation {
+    pub fn clear_trade(&mut self) {
+        self.trade_to = None;
+        self.trade_give = TradeOffer::default();
+        self.trade_take = TradeOffer::default();
+        self.countering = None;
+    }
+
+    pub fn clear_actions(&mut self) {
+        self.can_buy = false;
+        self.can_sell = false;
+        self.can_alter = false;
+        self.can_mortgage = false;
+        self.can_roll = false;
+        self.can_pay_bail = false;
+        self.can_use_card = false;
+        self.can_end = false;
+        self.can_declare = false;
+    }
 }
 
 pub fn gui( // separate this into multiple functions later
     mut stateful: ResMut<StatefulInformation>,
+    time: Res<Time>,
 
     tiles: Query<(Entity, &mut Tile, Option<&Corner>, Option<&Chance>, &ServerSide), (Without<Money>, Without<Position>)>,
     tokens: Query<(Entity, &mut Money, &Position, &ServerPlayer), (Without<Tile>, Without<Corner>, Without<Chance>)>,
+    specials: Query<(&ServerSide, Option<&Go>, Option<&Tax>, Option<&FreeParking>, Option<&Jail>, Option<&GoToJail>, Option<&Title>, Option<&Railroad>, Option<&Utility>)>,
 
     mut client: Client,
     mut contexts: EguiContexts
@@ -78,10 +119,106 @@ pub fn gui( // separate this into multiple functions later
             };
 
             ui.label(format!("Player ID: {:#?}", stateful.entity));
+            if let Some(seed) = stateful.seed {
+                ui.label(format!("Seed: {}", seed));
+            }
             ui.label(format!("Money: {}", *money.worth));
+            if let Some((deadline, warned)) = stateful.turn_clock {
+                let left = deadline - time.elapsed_seconds_f64();
+                if left > 0.0 && warned {
+                    ui.colored_label(egui::Color32::RED, format!("{:.0} seconds before your turn is played for you", left.ceil()));
+                } else if left > 0.0 {
+                    ui.label(format!("Turn time left: {:.0}s", left.ceil()));
+                }
+            }
+            if stateful.can_declare {
+                ui.label(format!("Raise {} to cover your debt", -*money.worth));
+            }
+            if !stateful.last_card.is_empty() {
+                ui.label(&stateful.last_card);
+            }
+            if !stateful.last_auction.is_empty() {
+                ui.label(&stateful.last_auction);
+            }
+            if !stateful.last_rejection.is_empty() {
+                ui.label(format!("Rejected: {}", stateful.last_rejection));
+            }
+
+            if let Some((tile, highest, leader, bidding)) = stateful.auction {
+                ui.label("Auction");
+                ui.separator();
+                ui.label(match leader {
+                    Some(leader) if leader == stateful.entity => format!("Space {:#?}, you lead with {}", tile, highest),
+                    Some(leader) => format!("Space {:#?}, {} leads with {}", tile, leader, highest),
+                    None => format!("Space {:#?}, no bids yet", tile)
+                });
+
+                if bidding && leader != Some(stateful.entity) {
+                    ui.horizontal(|row| {
+                        if *money.worth > highest { // an empty range would panic the clamp
+                            row.add(egui::DragValue::new(&mut stateful.bid).clamp_range(highest + 1..=*money.worth));
+                            if row.button("Bid").clicked() {
+                                client.send_message::<PlayerActionChannel, PlacedBid>(&PlacedBid { amount: stateful.bid });
+                            }
+                        }
+                        if row.button("Pass").clicked() {
+                            client.send_message::<PlayerActionChannel, PassBid>(&PassBid);
+                            stateful.auction = None;
+                        }
+                    });
+                }
+                ui.separator();
+            }
+
+            if *position.jailed {
+                ui.horizontal(|row| {
+                    row.label("In jail");
+                    if stateful.can_roll && row.button("Roll for doubles").clicked() {
+                        client.send_message::<PlayerActionChannel, RollDice>(&RollDice);
+                        stateful.clear_actions();
+                    }
+                    if stateful.can_pay_bail && row.button("Pay bail").clicked() {
+                        client.send_message::<PlayerActionChannel, PayBail>(&PayBail);
+                        stateful.clear_actions();
+                    }
+                    if stateful.can_use_card && row.button("Use card").clicked() {
+                        client.send_message::<PlayerActionChannel, UseJailCard>(&UseJailCard);
+                        stateful.clear_actions();
+                    }
+                });
+            }
+
+            let kind = specials.iter().find(|x| *x.0.id == *position.tile).map_or(String::new(), |(_, go, tax, parking, jail, go_to_jail, _, railroad, utility)| {
+                if let Some(railroad) = railroad {
+                    let owned = (*railroad.owned).clamp(1, railroad.rent.len());
+                    format!("Railroad, rent {} with {} owned", railroad.rent[owned - 1], *railroad.owned)
+                } else if let Some(utility) = utility {
+                    let owned = (*utility.owned).clamp(1, utility.multipliers.len());
+                    format!("Utility, rent {}x the roll with {} owned", utility.multipliers[owned - 1], *utility.owned)
+                } else if let Some(go) = go {
+                    format!("GO, collect {}", *go.salary)
+                } else if let Some(tax) = tax {
+                    if *tax.percentage { format!("Tax, {}% of net worth", *tax.amount) } else { format!("Tax, pay {}", *tax.amount) }
+                } else if let Some(parking) = parking {
+                    match *parking.jackpot {
+                        Some(jackpot) => format!("Free Parking, jackpot {}", jackpot),
+                        None => "Free Parking".to_string()
+                    }
+                } else if jail.is_some() {
+                    if *position.jailed { "In Jail".to_string() } else { "Just Visiting".to_string() }
+                } else if go_to_jail.is_some() {
+                    "Go To Jail".to_string()
+                } else {
+                    String::new()
+                }
+            });
 
             ui.horizontal(|row| {
-                row.label(format!("Space {:#?}", *position.tile)); // replace with names later
+                match specials.iter().find(|x| *x.0.id == *position.tile).and_then(|x| x.6) {
+                    Some(title) => row.label(&*title.name),
+                    None => row.label(format!("Space {:#?}", *position.tile))
+                };
+                row.label(kind);
                 if stateful.can_buy && row.button("Buy").clicked() {
                     client.send_message::<PlayerActionChannel, BuyOwnable>(&BuyOwnable);
                 }
@@ -89,40 +226,151 @@ pub fn gui( // separate this into multiple functions later
 
             ui.label("Properties");
             ui.separator();
-            tiles.for_each(|(_, tile, _, _, server_side)| { // add homes/houses later
+            tiles.for_each(|(_, tile, _, _, server_side)| {
                 if *tile.owner == Some(stateful.entity) {
                     ui.horizontal(|row| {
                         row.label(format!("{:#?}", *server_side.id));
+                        row.label(match *tile.houses {
+                            0 => "".to_string(),
+                            5 => "Hotel".to_string(),
+                            houses => format!("{} House(s)", houses)
+                        });
                         if stateful.can_sell && row.button("Sell").clicked() {
                             client.send_message::<PlayerActionChannel, SellOwnable>(&SellOwnable { id: *server_side.id });
                         }
+                        if stateful.can_alter && row.button("Build").clicked() {
+                            client.send_message::<PlayerActionChannel, AlterOwnable>(&AlterOwnable { id: *server_side.id, build: true });
+                        }
+                        if stateful.can_alter && *tile.houses > 0 && row.button("Sell House").clicked() {
+                            client.send_message::<PlayerActionChannel, AlterOwnable>(&AlterOwnable { id: *server_side.id, build: false });
+                        }
+                        if *tile.mortgaged {
+                            row.label("Mortgaged");
+                            if stateful.can_mortgage && row.button("Unmortgage").clicked() {
+                                client.send_message::<PlayerActionChannel, UnmortgageOwnable>(&UnmortgageOwnable { id: *server_side.id });
+                            }
+                        } else if stateful.can_mortgage && *tile.houses == 0 && row.button("Mortgage").clicked() {
+                            client.send_message::<PlayerActionChannel, MortgageOwnable>(&MortgageOwnable { id: *server_side.id });
+                        }
                     });
                 }
             });
 
+            ui.label("Trade");
+            ui.separator();
+            let entity = stateful.entity;
+            ui.horizontal(|row| {
+                row.label("With");
+                for (_, _, _, server_player) in &tokens {
+                    if *server_player.id != entity {
+                        row.selectable_value(&mut stateful.trade_to, Some(*server_player.id), format!("{:#?}", *server_player.id));
+                    }
+                }
+            });
+
+            if let Some(to) = stateful.trade_to {
+                let (their_worth, their_cards) = tokens.iter().find(|x| *x.3.id == to)
+                    .map_or((0, 0), |x| (*x.1.worth, *x.2.jail_cards));
+
+                ui.horizontal(|row| {
+                    row.label("Give");
+                    row.add(egui::DragValue::new(&mut stateful.trade_give.money).clamp_range(0..=(*money.worth).max(0)));
+                    row.label("Cards");
+                    row.add(egui::DragValue::new(&mut stateful.trade_give.jail_cards).clamp_range(0..=*position.jail_cards));
+                });
+                ui.horizontal(|row| {
+                    row.label("Take");
+                    row.add(egui::DragValue::new(&mut stateful.trade_take.money).clamp_range(0..=their_worth.max(0)));
+                    row.label("Cards");
+                    row.add(egui::DragValue::new(&mut stateful.trade_take.jail_cards).clamp_range(0..=their_cards));
+                });
+
+                tiles.for_each(|(_, tile, _, _, server_side)| {
+                    let offer = if *tile.owner == Some(entity) {
+                        &mut stateful.trade_give
+                    } else if *tile.owner == Some(to) {
+                        &mut stateful.trade_take
+                    } else {
+                        return;
+                    };
+
+                    let id = *server_side.id;
+                    let mut selected = offer.tiles.contains(&id);
+                    let label = format!("{} {:#?}", if *tile.owner == Some(entity) { "Give" } else { "Take" }, id);
+                    if ui.checkbox(&mut selected, label).changed() {
+                        if selected { offer.tiles.push(id); } else { offer.tiles.retain(|x| *x != id); }
+                    }
+                });
+
+                ui.horizontal(|row| {
+                    if row.button(if stateful.countering.is_some() { "Counter" } else { "Propose" }).clicked() {
+                        let (give, take) = (stateful.trade_give.clone(), stateful.trade_take.clone());
+                        match stateful.countering {
+                            Some(id) => client.send_message::<PlayerActionChannel, CounterTrade>(&CounterTrade { id, give, take }),
+                            None => client.send_message::<PlayerActionChannel, ProposeTrade>(&ProposeTrade { to, give, take })
+                        };
+                        stateful.clear_trade();
+                    }
+                    if row.button("Clear").clicked() {
+                        stateful.clear_trade();
+                    }
+                });
+            }
+
+            for (id, from, to, give, take) in stateful.trades.clone() {
+                ui.horizontal(|row| {
+                    row.label(format!("{:#?} gives {} for {}", from, describe(&give), describe(&take)));
+                    if to == entity {
+                        if row.button("Accept").clicked() {
+                            client.send_message::<PlayerActionChannel, AcceptTrade>(&AcceptTrade { id });
+                        }
+                        if row.button("Counter").clicked() { // edit their offer from our side
+                            stateful.trade_to = Some(from);
+                            stateful.trade_give = take.clone();
+                            stateful.trade_take = give.clone();
+                            stateful.countering = Some(id);
+                        }
+                    }
+                    if row.button(if from == entity { "Withdraw" } else { "Reject" }).clicked() {
+                        client.send_message::<PlayerActionChannel, RejectTrade>(&RejectTrade { id });
+                    }
+                });
+            }
+
             ui.separator();
             ui.horizontal(|row| {
                 if row.button("Forfeit").clicked() { // fix lose event
                     client.send_message::<PlayerActionChannel, Forfeit>(&Forfeit);
                 }
+                if stateful.can_declare && row.button("Declare Bankruptcy").clicked() {
+                    client.send_message::<PlayerActionChannel, DeclareBankruptcy>(&DeclareBankruptcy);
+                    stateful.clear_actions();
+                }
                 if stateful.can_end && row.button("End Turn").clicked() {
                     client.send_message::<PlayerActionChannel, EndTurn>(&EndTurn);
-                    stateful.can_buy = false;
-                    stateful.can_sell = false;
-                    stateful.can_end = false;
+                    stateful.clear_actions();
                 }
             });
         }
     });
 }
 
+fn describe(offer: &TradeOffer) -> String {
+    format!("{} money, {} space(s), {} card(s)", offer.money, offer.tiles.len(), offer.jail_cards)
+}
+
 pub fn begin_turn(
     mut stateful: ResMut<StatefulInformation>,
+    time: Res<Time>,
+
+    decks: Query<&Deck>,
 
     mut event_reader: EventReader<MessageEvents>
 ) {
     for events in event_reader.iter() {
         for turn in events.read::<BoardUpdateChannel, BeginTurn>() {
+            stateful.clear_actions(); // a turn can be offered more than once, like after leaving jail
+            stateful.last_rejection = "".into();
             for action in turn.available_actions {
                 match action {
                     Action::Sell => {
@@ -131,10 +379,28 @@ pub fn begin_turn(
                     Action::Purchase => {
                         stateful.can_buy = true;
                     }
-                    _ => {} // add more later
+                    Action::Alter => {
+                        stateful.can_alter = true;
+                    }
+                    Action::Mortgage => {
+                        stateful.can_mortgage = true;
+                    }
+                    Action::Roll => {
+                        stateful.can_roll = true;
+                    }
+                    Action::PayBail => {
+                        stateful.can_pay_bail = true;
+                    }
+                    Action::UseJailCard => {
+                        stateful.can_use_card = true;
+                    }
+                    Action::None => {
+                        stateful.can_end = true;
+                    }
+                    Action::DeclareBankruptcy => {
+                        stateful.can_declare = true;
+                    }
                 }
-
-                stateful.can_end = true;
             }
         }
 
@@ -142,14 +408,67 @@ pub fn begin_turn(
             stateful.entity = entity.id;
         }
 
-        for _ in events.read::<BoardUpdateChannel, StartGame>() {
+        for drawn in events.read::<BoardUpdateChannel, DrawCard>() {
+            if let Some(deck) = decks.iter().find(|deck| *deck.index == drawn.deck) {
+                let text = deck.cards.get(drawn.card).cloned().unwrap_or_default();
+                stateful.last_card = if drawn.player == stateful.entity {
+                    format!("You drew {}: {}", *deck.name, text)
+                } else {
+                    format!("{} drew {}: {}", drawn.player, *deck.name, text)
+                };
+            }
+        }
+
+        for auction in events.read::<BoardUpdateChannel, AuctionUpdate>() {
+            stateful.bid = stateful.bid.max(auction.highest + 1);
+            stateful.auction = Some((auction.tile, auction.highest, auction.leader, auction.bidding));
+        }
+
+        for ended in events.read::<BoardUpdateChannel, EndAuction>() {
+            stateful.auction = None;
+            stateful.bid = 0;
+            stateful.last_auction = match ended.winner {
+                Some(winner) if winner == stateful.entity => format!("You won space {:#?} for {}", ended.tile, ended.price),
+                Some(winner) => format!("{} won space {:#?} for {}", winner, ended.tile, ended.price),
+                None => format!("Nobody bid on space {:#?}", ended.tile)
+            };
+        }
+
+        for rejected in events.read::<BoardUpdateChannel, ActionRejected>() {
+            stateful.last_rejection = rejected.reason;
+        }
+
+        for offered in events.read::<BoardUpdateChannel, TradeOffered>() {
+            if !stateful.trades.iter().any(|x| x.0 == offered.id) {
+                stateful.trades.push((offered.id, offered.from, offered.to, offered.give, offered.take));
+            }
+        }
+
+        for closed in events.read::<BoardUpdateChannel, TradeClosed>() {
+            stateful.trades.retain(|x| x.0 != closed.id);
+            if stateful.countering == Some(closed.id) { stateful.clear_trade(); } // withdrawn while we were editing
+        }
+
+        for started in events.read::<BoardUpdateChannel, StartGame>() {
             stateful.started = true;
+            stateful.seed = Some(started.seed);
+        }
+
+        for timer in events.read::<BoardUpdateChannel, TurnTimer>() {
+            stateful.turn_clock = Some((time.elapsed_seconds_f64() + timer.seconds as f64, timer.warning));
         }
 
         for _ in events.read::<BoardUpdateChannel, EndGame>() {
             stateful.started = false;
             stateful.ready = false;
             stateful.entity = 0;
+            stateful.turn_clock = None;
+            stateful.last_card = "".into();
+            stateful.auction = None;
+            stateful.last_auction = "".into();
+            stateful.last_rejection = "".into();
+            stateful.trades.clear();
+            stateful.clear_trade();
         }
     }
 }
\ No newline at end of file
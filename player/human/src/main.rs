@@ -17,10 +17,28 @@ fn main() {
             url: "".into(),
             can_buy: false,
             can_sell: false,
+            can_alter: false,
+            can_mortgage: false,
+            can_roll: false,
+            can_pay_bail: false,
+            can_use_card: false,
             can_end: false,
+            can_declare: false,
             entity: 0,
             started: false,
+            seed: None,
+            turn_clock: None,
             ready: false,
+            last_card: "".into(),
+            auction: None,
+            bid: 0,
+            last_auction: "".into(),
+            last_rejection: "".into(),
+            trade_to: None,
+            trade_give: Default::default(),
+            trade_take: Default::default(),
+            countering: None,
+            trades: vec![],
         })
         .add_systems(
             (
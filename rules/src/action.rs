@@ -0,0 +1,131 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::{dice::Roll, trade::Offer};
+
+// Everything a player (or the board on their behalf) can ask of the game
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Roll, // from jail this is an attempt at doubles
+    PayBail,
+    UseJailCard,
+    Buy,
+    Sell(usize), // tile index
+    Build(usize), // one house on a tile, the fifth is a hotel
+    SellHouse(usize),
+    Mortgage(usize),
+    Unmortgage(usize),
+    Bid(i32), // during an auction, from any bidder
+    PassBid,
+    ProposeTrade { to: usize, give: Offer, take: Offer }, // any time, not just on the proposer's turn
+    AcceptTrade(usize), // trade id
+    RejectTrade(usize),
+    CounterTrade { trade: usize, give: Offer, take: Offer }, // rejects and proposes back in one step
+    EndTurn, // declining an ownable tile starts an auction first, ending in debt starts raising funds
+    DeclareBankruptcy, // only while raising funds, everything goes to the creditor
+    Forfeit
+}
+
+// Everything that happened because of an action, in order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    Rolled { player: usize, roll: Roll },
+    Moved { player: usize, tile: usize },
+    PassedGo { player: usize, amount: i32 },
+    PaidRent { player: usize, owner: usize, tile: usize, amount: i32 },
+    DrewCard { player: usize, deck: usize, card: usize },
+    Payment { from: Option<usize>, to: Option<usize>, amount: i32 }, // None is the bank
+    Bought { player: usize, tile: usize, price: i32 },
+    Sold { player: usize, tile: usize, price: i32 },
+    Built { player: usize, tile: usize, houses: usize, price: i32 },
+    SoldHouse { player: usize, tile: usize, houses: usize, price: i32 },
+    Mortgaged { player: usize, tile: usize, amount: i32 },
+    Unmortgaged { player: usize, tile: usize, amount: i32 },
+    AuctionStarted { tile: usize },
+    BidPlaced { player: usize, amount: i32 },
+    BidPassed { player: usize },
+    AuctionEnded { tile: usize, winner: Option<usize>, price: i32 },
+    TradeProposed { trade: usize, from: usize, to: usize },
+    TradeAccepted { trade: usize, from: usize, to: usize },
+    TradeRejected { trade: usize, from: usize, to: usize },
+    Jailed { player: usize },
+    LeftJail { player: usize },
+    RaisingFunds { player: usize, debt: i32 },
+    DebtSettled { player: usize },
+    TurnEnded { player: usize },
+    Bankrupt { player: usize, creditor: Option<usize> },
+    TurnLimit { standings: Vec<(usize, i32)> }, // GameState::standings when the cap was hit, always followed by GameOver
+    GameOver { winner: Option<usize> } // None on a stalemate
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    GameFinished,
+    UnknownPlayer,
+    UnknownTile,
+    NotYourTurn,
+    AlreadyRolled,
+    NotRolled,
+    NotInJail,
+    NoJailCard,
+    NotOwnable,
+    AlreadyOwned,
+    NotOwner,
+    NoMonopoly,
+    UnevenBuilding,
+    FullyBuilt,
+    NotBuildable,
+    NoHouses,
+    HasBuildings,
+    InsufficientFunds,
+    Mortgaged,
+    NotMortgaged,
+    AuctionInProgress,
+    NoAuction,
+    NotBidding,
+    BidTooLow,
+    LeadingBid,
+    InvalidTrade,
+    UnknownTrade,
+    NotTrading,
+    RaisingFunds,
+    NotInDebt
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Rejection::GameFinished => "the game has already finished",
+            Rejection::UnknownPlayer => "player is not in the game",
+            Rejection::UnknownTile => "tile does not exist",
+            Rejection::NotYourTurn => "it is not this player's turn",
+            Rejection::AlreadyRolled => "dice were already rolled this turn",
+            Rejection::NotRolled => "dice have not been rolled this turn",
+            Rejection::NotInJail => "player is not in jail",
+            Rejection::NoJailCard => "player has no get out of jail free card",
+            Rejection::NotOwnable => "tile cannot be owned",
+            Rejection::AlreadyOwned => "tile is already owned",
+            Rejection::NotOwner => "player does not own this tile",
+            Rejection::NoMonopoly => "player does not own the whole group",
+            Rejection::UnevenBuilding => "houses must be built and sold evenly across a group",
+            Rejection::FullyBuilt => "tile already has a hotel",
+            Rejection::NotBuildable => "houses can't be built on this tile",
+            Rejection::NoHouses => "tile has no houses to sell",
+            Rejection::HasBuildings => "group still has houses on it",
+            Rejection::InsufficientFunds => "player cannot afford this",
+            Rejection::Mortgaged => "tile is mortgaged",
+            Rejection::NotMortgaged => "tile is not mortgaged",
+            Rejection::AuctionInProgress => "an auction is in progress",
+            Rejection::NoAuction => "there is no auction in progress",
+            Rejection::NotBidding => "player is not bidding in this auction",
+            Rejection::BidTooLow => "bid must beat the highest bid",
+            Rejection::LeadingBid => "the leading bidder can't pass",
+            Rejection::InvalidTrade => "a trade needs another player and something to exchange",
+            Rejection::UnknownTrade => "trade does not exist",
+            Rejection::NotTrading => "player is not part of this trade",
+            Rejection::RaisingFunds => "a player is raising funds to cover a debt",
+            Rejection::NotInDebt => "player is not raising funds"
+        })
+    }
+}
+
+impl std::error::Error for Rejection {}
@@ -0,0 +1,38 @@
+use serde::{Serialize, Deserialize};
+
+// Bidding on a tile the current player declined to buy, the turn resumes once it settles
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Auction {
+    pub tile: usize,
+    pub bidders: Vec<usize>, // players still in, the leader can't pass
+    pub leader: Option<usize>,
+    pub highest: i32
+}
+
+impl Auction {
+    pub fn new(tile: usize, bidders: Vec<usize>) -> Self {
+        Self {
+            tile,
+            bidders,
+            leader: None,
+            highest: 0
+        }
+    }
+
+    // Everyone but the leader has passed, or everyone passed without bidding
+    pub fn is_settled(&self) -> bool {
+        match self.bidders.as_slice() {
+            [] => true,
+            [last] => self.leader == Some(*last),
+            _ => false
+        }
+    }
+
+    pub fn withdraw(&mut self, player: usize) {
+        self.bidders.retain(|bidder| *bidder != player);
+        if self.leader == Some(player) { // a bankrupt leader's bid is void
+            self.leader = None;
+            self.highest = 0;
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::{fmt, fs, path::Path};
+use serde::{Serialize, Deserialize};
+use crate::{card::{self, Deck, DeckDefinition}, tile::{TileState, TileKind, Levy, HOTEL}};
+
+// A board layout read from a .toml or .json file, tiles are listed in order starting from GO
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardDefinition {
+    pub name: String,
+    pub tiles: Vec<TileDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub decks: Vec<DeckDefinition> // chance tiles pick one by index, the standard decks if there are none
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TileDefinition {
+    pub name: String,
+    pub kind: Kind,
+    #[serde(default)]
+    pub group: Option<usize>,
+    #[serde(default)]
+    pub color: Option<String>, // "#rrggbb"
+    #[serde(default)]
+    pub price: i32,
+    #[serde(default)]
+    pub rent: Vec<i32>, // by house count, [0] is the bare rent. By how many are owned for railroads, and dice multipliers for utilities
+    #[serde(default)]
+    pub house_cost: i32, // 0 if houses can't be built
+    #[serde(default)]
+    pub deck: Option<usize>, // chance tiles only
+    #[serde(default)]
+    pub tax: Option<i32>, // fixed fee
+    #[serde(default)]
+    pub tax_percent: Option<i32> // of net worth
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Property,
+    Railroad,
+    Utility,
+    Chance,
+    Go,
+    Tax,
+    FreeParking,
+    Jail,
+    GoToJail,
+    Corner
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BoardError {
+    Io(String),
+    Format(String), // unknown extension
+    Parse(String),
+    Layout(String), // problems with the board as a whole
+    Tile { index: usize, name: String, reason: String },
+    Deck { index: usize, name: String, reason: String }
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::Io(error) => write!(f, "could not read board file: {}", error),
+            BoardError::Format(extension) => write!(f, "unknown board file type \"{}\", expected .toml or .json", extension),
+            BoardError::Parse(error) => write!(f, "could not parse board file: {}", error),
+            BoardError::Layout(reason) => write!(f, "invalid board: {}", reason),
+            BoardError::Tile { index, name, reason } => write!(f, "invalid tile {} ({}): {}", index, name, reason),
+            BoardError::Deck { index, name, reason } => write!(f, "invalid deck {} ({}): {}", index, name, reason)
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+impl BoardDefinition {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BoardError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| BoardError::Io(format!("{}: {}", path.display(), error)))?;
+
+        let definition = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&text)?,
+            Some("json") => Self::from_json(&text)?,
+            extension => return Err(BoardError::Format(extension.unwrap_or_default().to_string()))
+        };
+
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    // the real 40 square board, what the bots are trained on
+    pub fn classic() -> Self {
+        let definition = Self::from_toml(include_str!("../boards/classic.toml")).expect("Built-in classic board should parse");
+        definition.validate().expect("Built-in classic board should be valid");
+        definition
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, BoardError> {
+        toml::from_str(text).map_err(|error| BoardError::Parse(error.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, BoardError> {
+        serde_json::from_str(text).map_err(|error| BoardError::Parse(error.to_string()))
+    }
+
+    pub fn validate(&self) -> Result<(), BoardError> {
+        if self.tiles.is_empty() || !self.tiles.len().is_multiple_of(4) {
+            return Err(BoardError::Layout(format!("{} tiles can't be split evenly across four sides", self.tiles.len())));
+        }
+        if self.tiles[0].kind != Kind::Go {
+            return Err(BoardError::Layout("the first tile must be GO".to_string()));
+        }
+
+        let count = |kind: Kind| self.tiles.iter().filter(|tile| tile.kind == kind).count();
+        if count(Kind::Go) > 1 {
+            return Err(BoardError::Layout("there can only be one GO".to_string()));
+        }
+        if count(Kind::Jail) > 1 {
+            return Err(BoardError::Layout("there can only be one jail".to_string()));
+        }
+        if count(Kind::GoToJail) > 0 && count(Kind::Jail) == 0 {
+            return Err(BoardError::Layout("go to jail needs a jail tile".to_string()));
+        }
+
+        for (index, deck) in self.decks.iter().enumerate() {
+            deck.validate(self.tiles.len()).map_err(|reason| BoardError::Deck { index, name: deck.name.clone(), reason })?;
+        }
+        let decks = if self.decks.is_empty() { card::standard().len() } else { self.decks.len() };
+        for (index, tile) in self.tiles.iter().enumerate() {
+            tile.validate(decks).map_err(|reason| BoardError::Tile { index, name: tile.name.clone(), reason })?;
+        }
+
+        // groups index straight into a list, so every id up to the highest needs a tile
+        let groups = self.tiles.iter().filter_map(|tile| tile.group).max().map_or(0, |group| group + 1);
+        for group in 0..groups {
+            if !self.tiles.iter().any(|tile| tile.group == Some(group)) {
+                return Err(BoardError::Layout(format!("group {} has no tiles, group ids must count up from 0", group)));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn layout(&self) -> Vec<TileState> {
+        self.tiles.iter().map(TileDefinition::state).collect()
+    }
+
+    pub fn decks(&self) -> Vec<Deck> {
+        if self.decks.is_empty() { return card::decks(); }
+        self.decks.iter().map(DeckDefinition::deck).collect()
+    }
+}
+
+impl TileDefinition {
+    fn validate(&self, decks: usize) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("tile needs a name".to_string());
+        }
+        if let Some(color) = &self.color {
+            if color.len() != 7 || !color.starts_with('#') || !color[1..].chars().all(|digit| digit.is_ascii_hexdigit()) {
+                return Err(format!("color \"{}\" should look like #rrggbb", color));
+            }
+        }
+        if self.price < 0 {
+            return Err("price can't be negative".to_string());
+        }
+
+        if self.kind == Kind::Property {
+            if self.group.is_none() {
+                return Err("properties need a group".to_string());
+            }
+            if self.price == 0 {
+                return Err("properties need a price".to_string());
+            }
+            if self.rent.len() != HOTEL + 1 {
+                return Err(format!("rent needs {} entries, bare rent then 1 to {} houses, found {}", HOTEL + 1, HOTEL, self.rent.len()));
+            }
+            if self.rent.iter().any(|rent| *rent < 0) {
+                return Err("rent can't be negative".to_string());
+            }
+            if self.house_cost < 0 {
+                return Err("house cost can't be negative".to_string());
+            }
+        } else if matches!(self.kind, Kind::Railroad | Kind::Utility) {
+            if self.price == 0 {
+                return Err("railroads and utilities need a price".to_string());
+            }
+            if self.rent.is_empty() || self.rent.len() > HOTEL + 1 {
+                return Err(format!("rent needs 1 to {} entries, one for each number owned, found {}", HOTEL + 1, self.rent.len()));
+            }
+            if self.rent.iter().any(|rent| *rent < 0) {
+                return Err("rent can't be negative".to_string());
+            }
+            if self.group.is_some() || self.house_cost != 0 {
+                return Err("railroads and utilities can't have a group or house cost".to_string());
+            }
+        } else if self.group.is_some() || !self.rent.is_empty() || self.house_cost != 0 {
+            return Err("only properties can have a group, rent or house cost".to_string());
+        }
+
+        match self.kind {
+            Kind::Chance => match self.deck {
+                Some(deck) if deck < decks => {},
+                Some(deck) => return Err(format!("deck {} doesn't exist, there are {} decks", deck, decks)),
+                None => return Err("chance tiles need a deck".to_string())
+            },
+            _ if self.deck.is_some() => return Err("only chance tiles can have a deck".to_string()),
+            _ => {}
+        }
+
+        match (self.kind, self.tax, self.tax_percent) {
+            (Kind::Tax, Some(_), Some(_)) | (Kind::Tax, None, None) => return Err("tax tiles need exactly one of tax or tax_percent".to_string()),
+            (Kind::Tax, Some(amount), None) if amount < 0 => return Err("tax can't be negative".to_string()),
+            (Kind::Tax, None, Some(percent)) if !(0..=100).contains(&percent) => return Err("tax_percent must be between 0 and 100".to_string()),
+            (Kind::Tax, _, _) => {},
+            (_, None, None) => {},
+            _ => return Err("only tax tiles can have a tax".to_string())
+        }
+
+        Ok(())
+    }
+
+    // only called on validated definitions
+    fn state(&self) -> TileState {
+        let kind = match self.kind {
+            Kind::Property => {
+                let mut rent = [0; HOTEL + 1];
+                rent.copy_from_slice(&self.rent);
+                return TileState::property(self.group.expect("Validated property has a group"), self.price, rent, self.house_cost);
+            }
+            Kind::Railroad => return TileState::railroad(self.price, self.padded_rent()),
+            Kind::Utility => return TileState::utility(self.price, self.padded_rent()),
+            Kind::Chance => TileKind::Chance(self.deck.expect("Validated chance has a deck")),
+            Kind::Go => TileKind::Go,
+            Kind::Tax => match self.tax {
+                Some(amount) => TileKind::Tax(Levy::Fixed(amount)),
+                None => TileKind::Tax(Levy::Percent(self.tax_percent.expect("Validated tax has an amount")))
+            },
+            Kind::FreeParking => TileKind::FreeParking,
+            Kind::Jail => TileKind::Jail,
+            Kind::GoToJail => TileKind::GoToJail,
+            Kind::Corner => TileKind::Corner
+        };
+
+        TileState::new(kind, None, self.price)
+    }
+
+    // owning more railroads or utilities than listed charges the last entry
+    fn padded_rent(&self) -> [i32; HOTEL + 1] {
+        let last = *self.rent.last().expect("Validated rent has entries");
+        let mut rent = [last; HOTEL + 1];
+        rent[..self.rent.len()].copy_from_slice(&self.rent);
+        rent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(name: &str, kind: Kind) -> TileDefinition {
+        TileDefinition { name: name.to_string(), kind, group: None, color: None, price: 0, rent: vec![], house_cost: 0, deck: None, tax: None, tax_percent: None }
+    }
+
+    // one of each kind that has its own checks
+    fn board() -> BoardDefinition {
+        BoardDefinition {
+            name: "Small".to_string(),
+            tiles: vec![
+                tile("Go", Kind::Go),
+                TileDefinition { group: Some(0), color: Some("#8b4513".to_string()), price: 60, rent: vec![2, 10, 30, 90, 160, 250], house_cost: 50, ..tile("Old Kent Road", Kind::Property) },
+                TileDefinition { deck: Some(0), ..tile("Chance", Kind::Chance) },
+                TileDefinition { tax: Some(200), ..tile("Income Tax", Kind::Tax) },
+                tile("Jail", Kind::Jail),
+                TileDefinition { price: 200, rent: vec![25, 50, 100, 200], ..tile("Kings Cross", Kind::Railroad) },
+                TileDefinition { price: 150, rent: vec![4, 10], ..tile("Electric Company", Kind::Utility) },
+                tile("Go To Jail", Kind::GoToJail)
+            ],
+            decks: vec![]
+        }
+    }
+
+    fn tile_error(definition: &BoardDefinition) -> Option<usize> {
+        match definition.validate() {
+            Err(BoardError::Tile { index, .. }) => Some(index),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn valid_board_lays_out() {
+        let definition = board();
+        assert_eq!(definition.validate(), Ok(()));
+
+        let layout = definition.layout();
+        assert_eq!(layout[3].kind, TileKind::Tax(Levy::Fixed(200)));
+        assert_eq!(layout[5].rent, [25, 50, 100, 200, 200, 200]); // owning more than listed charges the last
+    }
+
+    #[test]
+    fn tile_count_must_split_into_sides() {
+        let mut definition = board();
+        definition.tiles.pop();
+        assert!(matches!(definition.validate(), Err(BoardError::Layout(_))));
+
+        definition.tiles.clear();
+        assert!(matches!(definition.validate(), Err(BoardError::Layout(_))));
+    }
+
+    #[test]
+    fn first_tile_must_be_the_only_go() {
+        let mut definition = board();
+        definition.tiles.swap(0, 4);
+        assert!(matches!(definition.validate(), Err(BoardError::Layout(_))));
+
+        let mut definition = board();
+        definition.tiles[4] = tile("Go Again", Kind::Go);
+        assert!(matches!(definition.validate(), Err(BoardError::Layout(_))));
+    }
+
+    #[test]
+    fn groups_count_up_from_zero() {
+        let mut definition = board();
+        definition.tiles[1].group = Some(1);
+        assert!(matches!(definition.validate(), Err(BoardError::Layout(reason)) if reason.contains("group 0")));
+    }
+
+    #[test]
+    fn colors_are_hex() {
+        for color in ["8b4513", "#8b451", "#8b451g", "#8b45133"] {
+            let mut definition = board();
+            definition.tiles[1].color = Some(color.to_string());
+            assert_eq!(tile_error(&definition), Some(1), "{}", color);
+        }
+    }
+
+    #[test]
+    fn rent_tables_have_the_right_length() {
+        let mut definition = board();
+        definition.tiles[1].rent.pop();
+        assert_eq!(tile_error(&definition), Some(1));
+
+        let mut definition = board();
+        definition.tiles[5].rent = vec![25; HOTEL + 2];
+        assert_eq!(tile_error(&definition), Some(5));
+
+        definition.tiles[5].rent.clear();
+        assert_eq!(tile_error(&definition), Some(5));
+    }
+
+    #[test]
+    fn decks_must_exist() {
+        let mut definition = board();
+        definition.tiles[2].deck = Some(2);
+        assert_eq!(tile_error(&definition), Some(2));
+
+        definition.tiles[2].deck = None;
+        assert_eq!(tile_error(&definition), Some(2));
+    }
+
+    #[test]
+    fn boards_can_bring_their_own_decks() {
+        let mut definition = board();
+        assert_eq!(definition.decks(), card::decks());
+
+        definition.decks = vec![DeckDefinition { name: "Only".to_string(), cards: vec![card::Card::new("Back to GO", card::Effect::Advance(0))] }];
+        definition.tiles[2].deck = Some(1);
+        assert_eq!(tile_error(&definition), Some(2)); // the standard second deck is gone
+        definition.tiles[2].deck = Some(0);
+        assert_eq!(definition.validate(), Ok(()));
+        assert_eq!(definition.decks()[0].name, "Only");
+
+        definition.decks[0].cards[0].effect = card::Effect::Advance(8);
+        assert!(matches!(definition.validate(), Err(BoardError::Deck { index: 0, .. })));
+    }
+
+    #[test]
+    fn taxes_need_exactly_one_amount() {
+        let mut definition = board();
+        definition.tiles[3].tax = None;
+        assert_eq!(tile_error(&definition), Some(3));
+
+        definition.tiles[3].tax = Some(200);
+        definition.tiles[3].tax_percent = Some(10);
+        assert_eq!(tile_error(&definition), Some(3));
+
+        definition.tiles[3].tax = None;
+        definition.tiles[3].tax_percent = Some(101);
+        assert_eq!(tile_error(&definition), Some(3));
+    }
+
+    #[test]
+    fn boards_round_trip_through_toml_and_json() {
+        let mut definition = board();
+        let folder = std::env::temp_dir().join(format!("monai-{}-boards", std::process::id()));
+        fs::create_dir_all(&folder).expect("Creating the folder");
+
+        let toml = folder.join("small.toml");
+        fs::write(&toml, toml::to_string(&definition).expect("Writing TOML")).expect("Saving TOML");
+        assert_eq!(BoardDefinition::load(&toml), Ok(definition.clone()));
+
+        definition.decks = card::standard(); // the toml writer can't do card effects, reading them is covered below
+        let json = folder.join("small.json");
+        fs::write(&json, serde_json::to_string(&definition).expect("Writing JSON")).expect("Saving JSON");
+        assert_eq!(BoardDefinition::load(&json), Ok(definition));
+
+        let text = folder.join("small.txt");
+        fs::write(&text, "").expect("Saving text");
+        assert_eq!(BoardDefinition::load(&text), Err(BoardError::Format("txt".to_string())));
+        assert!(matches!(BoardDefinition::load(folder.join("missing.toml")), Err(BoardError::Io(_))));
+        assert!(matches!(BoardDefinition::from_toml("name = \"Small\""), Err(BoardError::Parse(_))));
+
+        fs::remove_dir_all(folder).expect("Cleaning up");
+    }
+
+    #[test]
+    fn decks_read_from_toml() {
+        let mut text = toml::to_string(&board()).expect("Writing TOML");
+        text.push_str(r#"
+[[decks]]
+name = "Only"
+cards = [
+    { text = "Advance to GO", effect = { advance = 0 } },
+    { text = "Get out of jail free", effect = "get_out_of_jail" },
+    { text = "Repairs", effect = { repairs = { house = 25, hotel = 100 } } }
+]
+"#);
+
+        let definition = BoardDefinition::from_toml(&text).expect("Reading TOML");
+        assert_eq!(definition.validate(), Ok(()));
+        assert_eq!(definition.decks[0].cards[2].effect, card::Effect::Repairs { house: 25, hotel: 100 });
+    }
+
+    #[test]
+    fn classic_board_is_valid() {
+        assert_eq!(BoardDefinition::classic().tiles.len(), 40);
+    }
+}
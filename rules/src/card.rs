@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    Advance(usize), // to a tile index, collecting GO on the way
+    Back(usize), // spaces, never collects GO
+    Collect(i32),
+    Pay(i32),
+    CollectFromEach(i32),
+    PayEach(i32),
+    GoToJail,
+    GetOutOfJail, // kept by the player until used
+    Repairs { house: i32, hotel: i32 }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Card {
+    pub text: String,
+    pub effect: Effect
+}
+
+impl Card {
+    pub fn new(text: &str, effect: Effect) -> Self {
+        Self {
+            text: text.to_string(),
+            effect
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Deck {
+    pub name: String,
+    pub cards: Vec<Card>,
+    pub pile: VecDeque<usize> // draw order, cards go back on the bottom
+}
+
+impl Deck {
+    pub fn new(name: &str, cards: Vec<Card>) -> Self {
+        Self {
+            name: name.to_string(),
+            pile: (0..cards.len()).collect(),
+            cards
+        }
+    }
+
+    pub fn shuffle<R: Rng>(&mut self, random: &mut R) {
+        let mut order: Vec<usize> = self.pile.drain(..).collect();
+        order.shuffle(random);
+        self.pile = order.into();
+    }
+
+    pub fn draw(&mut self) -> Option<usize> {
+        self.pile.pop_front()
+    }
+
+    pub fn put_back(&mut self, card: usize) {
+        self.pile.push_back(card);
+    }
+}
+
+// A deck as it's written in a data file, without the pile
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeckDefinition {
+    pub name: String,
+    pub cards: Vec<Card>
+}
+
+impl DeckDefinition {
+    pub fn deck(&self) -> Deck {
+        Deck::new(&self.name, self.cards.clone())
+    }
+
+    // tiles is the board size, advance cards point into it
+    pub fn validate(&self, tiles: usize) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("deck needs a name".to_string());
+        }
+        if self.cards.is_empty() {
+            return Err("deck needs at least one card".to_string());
+        }
+
+        for (index, card) in self.cards.iter().enumerate() {
+            let problem = match card.effect {
+                _ if card.text.trim().is_empty() => Some("needs text".to_string()),
+                Effect::Advance(tile) if tile >= tiles => Some(format!("advances to tile {}, the board has {}", tile, tiles)),
+                Effect::Collect(amount) | Effect::Pay(amount) | Effect::CollectFromEach(amount) | Effect::PayEach(amount) if amount < 0 => Some("amount can't be negative".to_string()),
+                Effect::Repairs { house, hotel } if house < 0 || hotel < 0 => Some("repairs can't be negative".to_string()),
+                _ => None
+            };
+            if let Some(problem) = problem {
+                return Err(format!("card {} ({}) {}", index, card.text, problem));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeckFile {
+    decks: Vec<DeckDefinition>
+}
+
+// the standard Chance and Community Chest, for boards that don't bring their own
+pub fn standard() -> Vec<DeckDefinition> {
+    let file: DeckFile = toml::from_str(include_str!("../decks/standard.toml")).expect("Built-in decks should parse");
+    file.decks
+}
+
+// chance tiles pick one by index
+pub fn decks() -> Vec<Deck> {
+    standard().iter().map(DeckDefinition::deck).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_decks_are_valid() {
+        let decks = standard();
+        assert_eq!(decks.iter().map(|deck| deck.name.as_str()).collect::<Vec<_>>(), vec!["Chance", "Community Chest"]);
+        for deck in decks.iter() {
+            assert_eq!(deck.validate(40), Ok(()));
+        }
+        assert_eq!(decks[0].cards[8].effect, Effect::Repairs { house: 25, hotel: 100 });
+    }
+
+    #[test]
+    fn cards_are_checked_against_the_board() {
+        let mut deck = DeckDefinition { name: "Test".to_string(), cards: vec![Card::new("Advance", Effect::Advance(39))] };
+        assert_eq!(deck.validate(40), Ok(()));
+        assert!(deck.validate(20).is_err());
+
+        deck.cards[0] = Card::new("Pay", Effect::Pay(-5));
+        assert!(deck.validate(40).is_err());
+        deck.cards.clear();
+        assert!(deck.validate(40).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roll(pub usize, pub usize);
+
+impl Roll {
+    pub fn total(&self) -> usize {
+        self.0 + self.1
+    }
+
+    pub fn is_double(&self) -> bool {
+        self.0 == self.1
+    }
+}
+
+pub fn roll<R: Rng>(random: &mut R) -> Roll {
+    Roll(random.gen_range(1..=6), random.gen_range(1..=6))
+}
+
+// The game's only source of randomness. ChaCha gives the same numbers on every platform and rand version, so a seed replays a game exactly
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(into = "Stream", from = "Stream")]
+pub struct Random(ChaCha8Rng);
+
+// where a Random is in its stream, enough to carry on from the same number after a restore
+#[derive(Serialize, Deserialize)]
+struct Stream {
+    seed: [u8; 32],
+    stream: u64,
+    word: u128
+}
+
+impl From<Random> for Stream {
+    fn from(random: Random) -> Self {
+        Stream { seed: random.0.get_seed(), stream: random.0.get_stream(), word: random.0.get_word_pos() }
+    }
+}
+
+impl From<Stream> for Random {
+    fn from(stream: Stream) -> Self {
+        let mut random = ChaCha8Rng::from_seed(stream.seed);
+        random.set_stream(stream.stream);
+        random.set_word_pos(stream.word);
+        Random(random)
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random::new(0)
+    }
+}
+
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{card::{Deck, Effect}, state::{DOUBLES_LIMIT, JAIL_ROLLS}, tile::{TileState, TileKind}};
+
+const PRECISION: f64 = 1e-12; // total change in the distribution between sweeps
+const SWEEPS: usize = 1000; // a backstop, sweeping in the direction of travel settles even huge boards in far fewer
+const CARD_CHAIN: usize = 4; // cards moving onto more card tiles, past this the player just stays
+
+// Steady-state chance that a landing happens on each tile, summing to 1. A landing is anything that resolves a tile,
+// so a card that moves the player counts as landing on both. Jailed players are assumed to roll for doubles instead of paying,
+// and cards are drawn at random rather than in pile order
+pub fn probabilities(tiles: &[TileState], decks: &[Deck]) -> Vec<f32> {
+    if tiles.is_empty() { return vec![]; }
+
+    let chain = Chain::new(tiles, decks);
+    let mut incoming: Vec<Vec<(usize, f64)>> = vec![vec![]; chain.states()];
+    for state in 0..chain.states() {
+        chain.each(state, |probability, to, _| incoming[to].push((state, probability)));
+    }
+
+    // Gauss-Seidel, each state takes the mass flowing into it using what was already updated this sweep.
+    // Players mostly move forward, so one sweep in tile order carries mass all the way around the board
+    let mut distribution = vec![1.0 / chain.states() as f64; chain.states()];
+    for _ in 0..SWEEPS {
+        let mut change = 0.0;
+        for state in 0..distribution.len() {
+            let (mut mass, mut stay) = (0.0, 0.0);
+            for (from, probability) in incoming[state].iter() {
+                if *from == state { stay += probability; } else { mass += distribution[*from] * probability; }
+            }
+
+            let next = mass / (1.0 - stay);
+            change += (next - distribution[state]).abs();
+            distribution[state] = next;
+        }
+
+        let total: f64 = distribution.iter().sum();
+        distribution.iter_mut().for_each(|mass| *mass /= total);
+        if change < PRECISION { break; }
+    }
+
+    let mut landings = vec![0.0; tiles.len()];
+    for (state, mass) in distribution.iter().enumerate() {
+        chain.each(state, |probability, _, landed| {
+            for tile in landed { landings[*tile] += mass * probability; }
+        });
+    }
+
+    let total: f64 = landings.iter().sum();
+    landings.iter().map(|landing| (landing / total) as f32).collect()
+}
+
+// Layouts solved so far, so a board played game after game is only solved the first time
+#[derive(Default)]
+pub struct Cache {
+    solved: Vec<(Vec<TileState>, Vec<Deck>, Vec<f32>)> // oldest first
+}
+
+const CACHED: usize = 8; // generated boards change with every slider, only the latest few are kept
+
+impl Cache {
+    pub fn probabilities(&mut self, tiles: &[TileState], decks: &[Deck]) -> Vec<f32> {
+        if let Some((_, _, solved)) = self.solved.iter().find(|(known, cards, _)| known == tiles && cards == decks) {
+            return solved.clone();
+        }
+
+        let solved = probabilities(tiles, decks);
+        if self.solved.len() >= CACHED { self.solved.remove(0); }
+        self.solved.push((tiles.to_vec(), decks.to_vec(), solved.clone()));
+        solved
+    }
+}
+
+#[derive(Clone, Copy)]
+enum End {
+    Tile(usize),
+    Jail
+}
+
+struct Outcome {
+    probability: f64,
+    landed: Vec<usize>,
+    end: End
+}
+
+// States are a tile and the doubles rolled so far this turn, then one per failed attempt at leaving jail
+struct Chain {
+    jail: usize,
+    outcomes: Vec<Vec<Outcome>> // of landing on each tile
+}
+
+impl Chain {
+    fn new(tiles: &[TileState], decks: &[Deck]) -> Self {
+        Self {
+            jail: tiles.iter().position(|tile| tile.kind == TileKind::Jail).unwrap_or(0),
+            outcomes: (0..tiles.len()).map(|tile| resolve(tiles, decks, tile, CARD_CHAIN)).collect()
+        }
+    }
+
+    fn states(&self) -> usize {
+        self.outcomes.len() * DOUBLES_LIMIT + JAIL_ROLLS
+    }
+
+    fn jailed(&self, attempts: usize) -> usize {
+        self.outcomes.len() * DOUBLES_LIMIT + attempts
+    }
+
+    // every roll from a state, as its probability, the next state and the tiles landed on
+    fn each(&self, state: usize, mut visit: impl FnMut(f64, usize, &[usize])) {
+        let tiles = self.outcomes.len();
+
+        for first in 1..=6 {
+            for second in 1..=6 {
+                let probability = 1.0 / 36.0;
+                let double = first == second;
+
+                let (from, doubles) = if state < tiles * DOUBLES_LIMIT {
+                    let doubles = state % DOUBLES_LIMIT;
+                    if double && doubles + 1 >= DOUBLES_LIMIT {
+                        visit(probability, self.jailed(0), &[]);
+                        continue;
+                    }
+                    (state / DOUBLES_LIMIT, if double { doubles + 1 } else { 0 })
+                } else {
+                    let attempts = state - tiles * DOUBLES_LIMIT;
+                    if !double && attempts + 1 < JAIL_ROLLS {
+                        visit(probability, self.jailed(attempts + 1), &[]);
+                        continue;
+                    }
+                    (self.jail, 0) // out on doubles or forced bail, with no extra roll
+                };
+
+                for outcome in self.outcomes[(from + first + second) % tiles].iter() {
+                    let next = match outcome.end {
+                        End::Tile(tile) => tile * DOUBLES_LIMIT + doubles,
+                        End::Jail => self.jailed(0)
+                    };
+                    visit(probability * outcome.probability, next, &outcome.landed);
+                }
+            }
+        }
+    }
+}
+
+// Where landing on a tile can leave the player, following cards that move them
+fn resolve(tiles: &[TileState], decks: &[Deck], tile: usize, depth: usize) -> Vec<Outcome> {
+    let stay = |probability: f64| Outcome { probability, landed: vec![tile], end: End::Tile(tile) };
+
+    match tiles[tile].kind {
+        TileKind::GoToJail => vec![Outcome { probability: 1.0, landed: vec![tile], end: End::Jail }],
+        TileKind::Chance(deck) if depth > 0 => {
+            let cards = match decks.get(deck) {
+                Some(deck) if !deck.cards.is_empty() => &deck.cards,
+                _ => return vec![stay(1.0)]
+            };
+            let probability = 1.0 / cards.len() as f64;
+
+            let mut outcomes = vec![];
+            for card in cards.iter() {
+                let moved = match card.effect {
+                    Effect::Advance(to) => to % tiles.len(),
+                    Effect::Back(spaces) => (tile + tiles.len() - spaces % tiles.len()) % tiles.len(),
+                    Effect::GoToJail => {
+                        outcomes.push(Outcome { probability, landed: vec![tile], end: End::Jail });
+                        continue;
+                    }
+                    _ => {
+                        outcomes.push(stay(probability));
+                        continue;
+                    }
+                };
+
+                for then in resolve(tiles, decks, moved, depth - 1) {
+                    outcomes.push(Outcome { probability: probability * then.probability, landed: [vec![tile], then.landed].concat(), end: then.end });
+                }
+            }
+            outcomes
+        }
+        _ => vec![stay(1.0)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, Event, GameState, board::BoardDefinition, card, state::Settings, tile};
+
+    // Plays the classic board with the real rules, nobody buying and jailed players always rolling, and counts landings
+    fn simulate(turns: usize) -> Vec<f64> {
+        let tiles = BoardDefinition::classic().layout();
+        let settings = Settings { starting_money: 1_000_000, turn_limit: usize::MAX, seed: 11, ..Settings::default() };
+        let mut game = GameState::new(tiles.clone(), card::decks(), 2, settings);
+        let mut landings = vec![0.0; tiles.len()];
+
+        let mut play = |game: &mut GameState, player: usize, action: Action| {
+            let events = game.apply(player, action).expect("Simulated action was rejected");
+            for (index, event) in events.iter().enumerate() {
+                if let Event::Moved { tile, .. } = event {
+                    if events.get(index + 1) != Some(&Event::Jailed { player }) { landings[*tile] += 1.0; }
+                }
+            }
+        };
+
+        while game.turns < turns {
+            let player = game.current;
+            play(&mut game, player, Action::Roll);
+            if game.rolled { play(&mut game, player, Action::EndTurn); }
+            while let Some(auction) = game.auction.clone() {
+                play(&mut game, auction.bidders[0], Action::PassBid);
+            }
+
+            // the model draws from full decks, so kept jail cards go straight back
+            for player in game.players.iter_mut() {
+                for (deck, card) in player.jail_cards.drain(..) { game.decks[deck].put_back(card); }
+            }
+        }
+
+        let total: f64 = landings.iter().sum();
+        landings.iter().map(|landing| landing / total).collect()
+    }
+
+    #[test]
+    fn classic_matches_simulated_games() {
+        let solved = probabilities(&BoardDefinition::classic().layout(), &card::decks());
+        let simulated = simulate(200_000);
+
+        for (tile, (solved, simulated)) in solved.iter().zip(simulated.iter()).enumerate() {
+            assert!((*solved as f64 - simulated).abs() < 0.002, "tile {} solved {} simulated {}", tile, solved, simulated);
+        }
+    }
+
+    #[test]
+    fn classic_known_figures() {
+        let solved = probabilities(&BoardDefinition::classic().layout(), &card::decks());
+
+        assert!((solved.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        for (tile, expected) in [(0, 0.0373), (10, 0.0222), (19, 0.0302), (30, 0.0258), (39, 0.0208)] {
+            assert!((solved[tile] - expected).abs() < 1e-4, "tile {} is {}, expected {}", tile, solved[tile], expected);
+        }
+
+        // cards pull players onto GO, and the tiles a roll or two out of jail are the busiest properties
+        let busiest = (1..solved.len()).max_by(|first, second| solved[*first].total_cmp(&solved[*second]));
+        assert_eq!(busiest, Some(19));
+    }
+
+    #[test]
+    fn large_boards_settle() {
+        let solved = probabilities(&tile::generate(8, 250), &card::decks());
+
+        assert_eq!(solved.len(), 2000);
+        assert!((solved.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!(solved.iter().all(|probability| probability.is_finite() && *probability >= 0.0));
+    }
+
+    #[test]
+    fn cache_solves_a_layout_once() {
+        let mut cache = Cache::default();
+        let (classic, generated) = (BoardDefinition::classic().layout(), tile::generate(4, 10));
+
+        let first = cache.probabilities(&classic, &card::decks());
+        cache.probabilities(&generated, &card::decks());
+        assert_eq!(cache.probabilities(&classic, &card::decks()), first);
+        assert_eq!(cache.solved.len(), 2);
+    }
+}
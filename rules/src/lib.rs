@@ -0,0 +1,17 @@
+// Plain game logic, no Bevy or naia. The board drives this and mirrors it into replicated components
+pub mod action;
+pub mod auction;
+pub mod board;
+pub mod card;
+pub mod dice;
+pub mod landing;
+pub mod player;
+pub mod policy;
+pub mod replay;
+pub mod snapshot;
+pub mod state;
+pub mod tile;
+pub mod trade;
+
+pub use action::{Action, Event, Rejection};
+pub use state::GameState;
@@ -0,0 +1,28 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerState {
+    pub money: i32,
+    pub position: usize, // index into GameState.tiles
+    pub jail_cards: Vec<(usize, usize)>, // (deck, card) held until used
+    pub jailed: Option<usize>, // failed rolls for doubles while in jail
+    pub debts: Vec<(Option<usize>, i32)>, // who was paid past what the player had and how much, oldest first, None is the bank
+    pub bankrupt: bool
+}
+
+impl PlayerState {
+    pub fn new(money: i32) -> Self {
+        Self {
+            money,
+            position: 0,
+            jail_cards: vec![],
+            jailed: None,
+            debts: vec![],
+            bankrupt: false
+        }
+    }
+
+    pub fn creditor(&self) -> Option<usize> { // the last payment into debt, they get everything on bankruptcy
+        self.debts.last().and_then(|(creditor, _)| *creditor)
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{Action, GameState, state::BAIL};
+
+// Plays for a player who ran out of time. None once there's nothing left for them to do this turn,
+// the board still rolls at the start of a turn and runs auctions on its own
+
+const RESERVE: i32 = 200; // cash the heuristic keeps back for rent before spending
+
+// The passive default: out of jail by rolling, raise only what's owed, then end the turn
+pub fn end_turn(state: &GameState, player: usize) -> Option<Action> {
+    let available = state.available_actions(player);
+    if state.raising == Some(player) { return raise(&available); }
+
+    finish(&available)
+}
+
+// A simple bot, buys and builds while it keeps a reserve, pays its way out of jail when it can spare it
+pub fn heuristic(state: &GameState, player: usize) -> Option<Action> {
+    let available = state.available_actions(player);
+    if state.raising == Some(player) { return raise(&available); }
+
+    let money = state.players[player].money;
+    let spare = |cost: i32| money - cost >= RESERVE;
+
+    if available.contains(&Action::UseJailCard) { return Some(Action::UseJailCard); }
+    if available.contains(&Action::PayBail) && spare(BAIL) { return Some(Action::PayBail); }
+    if available.contains(&Action::Buy) && spare(state.tiles[state.players[player].position].cost) { return Some(Action::Buy); }
+
+    let improve = available.iter().find(|action| match action {
+        Action::Unmortgage(tile) => spare(state.unmortgage_cost(*tile)),
+        Action::Build(tile) => spare(state.tiles[*tile].house_cost),
+        _ => false
+    });
+    if let Some(action) = improve { return Some(action.clone()); }
+
+    finish(&available)
+}
+
+fn finish(available: &[Action]) -> Option<Action> {
+    [Action::EndTurn, Action::Roll].into_iter().find(|action| available.contains(action))
+}
+
+// least lost first, mortgages, then houses, then tiles, and bankruptcy once nothing is left
+fn raise(available: &[Action]) -> Option<Action> {
+    let first = |wanted: fn(&Action) -> bool| available.iter().find(|action| wanted(action)).cloned();
+
+    first(|action| matches!(action, Action::Mortgage(_)))
+        .or_else(|| first(|action| matches!(action, Action::SellHouse(_))))
+        .or_else(|| first(|action| matches!(action, Action::Sell(_))))
+        .or_else(|| first(|action| *action == Action::DeclareBankruptcy))
+}
@@ -0,0 +1,277 @@
+use std::{fmt, fs::{self, File, OpenOptions}, io::{self, BufWriter, Seek, SeekFrom, Write}, path::{Path, PathBuf}};
+use serde::{Serialize, Deserialize};
+use crate::{Action, Event, GameState, board::BoardDefinition, card::Deck, state::Settings, tile::TileState};
+
+pub const VERSION: u32 = 2; // bump whenever a record changes shape, older logs are refused instead of misread
+
+// One line of a .jsonl game log, the header always comes first
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum Record {
+    Game(Header),
+    Action(Step),
+    Reward(Reward),
+    End(End)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub version: u32,
+    pub settings: Settings, // the seed is in here
+    pub players: Vec<String>, // by seat
+    pub board: Option<BoardDefinition>, // None for generated boards
+    pub tiles: Vec<TileState>,
+    pub decks: Vec<Deck> // before shuffling, the seed takes care of that
+}
+
+// An action the rules accepted, rejected ones never change the game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    pub seat: usize,
+    pub action: Action,
+    pub events: Vec<Event> // rolls, moves, purchases, rent and so on
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reward {
+    pub seat: usize,
+    pub reward: f32
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct End {
+    pub winner: Option<usize>,
+    pub turns: usize,
+    pub standings: Vec<(usize, i32)> // GameState::standings at the end
+}
+
+// Writes a game as it's played, a line at a time so a crash only loses the last one
+pub struct Log {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    length: u64 // bytes written so far, snapshots keep it to cut off anything logged after them
+}
+
+impl Log {
+    pub fn create(path: impl AsRef<Path>, header: Header) -> io::Result<Self> {
+        if let Some(folder) = path.as_ref().parent() {
+            fs::create_dir_all(folder)?;
+        }
+
+        let path = path.as_ref().to_path_buf();
+        let mut log = Self { writer: BufWriter::new(File::create(&path)?), path, length: 0 };
+        log.write(&Record::Game(header))?;
+        Ok(log)
+    }
+
+    // Carries on an existing log from the first `length` bytes, dropping whatever came after
+    pub fn resume(path: impl AsRef<Path>, length: u64) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new().write(true).open(&path)?;
+        file.set_len(length)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(Self { writer: BufWriter::new(file), path, length })
+    }
+
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.writer.flush()?;
+        self.length += line.len() as u64;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogError {
+    Io(String),
+    Parse { line: usize, reason: String },
+    Version(u32),
+    Header, // the first line isn't a game record
+    Diverged { step: usize } // replaying didn't give the recorded events, the rules have changed since
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogError::Io(error) => write!(f, "could not read game log: {}", error),
+            LogError::Parse { line, reason } => write!(f, "could not parse game log line {}: {}", line, reason),
+            LogError::Version(version) => write!(f, "game log is version {}, only version {} can be read", version, VERSION),
+            LogError::Header => write!(f, "game log doesn't start with a game record"),
+            LogError::Diverged { step } => write!(f, "game log diverges from the rules at step {}", step)
+        }
+    }
+}
+
+impl std::error::Error for LogError {}
+
+// A loaded log, steps count from 0 in the order they were applied
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub header: Header,
+    pub steps: Vec<Step>,
+    pub rewards: Vec<(usize, Reward)>, // issued once this many steps were applied
+    pub end: Option<End> // None if the game was cut short
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LogError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| LogError::Io(format!("{}: {}", path.display(), error)))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, LogError> {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let parse_error = |line: usize, error: serde_json::Error| LogError::Parse { line: line + 1, reason: error.to_string() };
+
+        // the version is checked before anything else, a newer header might not parse at all
+        let (number, first) = lines.next().ok_or(LogError::Header)?;
+        let value: serde_json::Value = serde_json::from_str(first).map_err(|error| parse_error(number, error))?;
+        if value["record"] != "game" { return Err(LogError::Header); }
+        let version = value["version"].as_u64().ok_or(LogError::Header)? as u32;
+        if version != VERSION { return Err(LogError::Version(version)); }
+
+        let header = match serde_json::from_value(value).map_err(|error| parse_error(number, error))? {
+            Record::Game(header) => header,
+            _ => return Err(LogError::Header)
+        };
+
+        let mut replay = Self { header, steps: vec![], rewards: vec![], end: None };
+        for (number, line) in lines {
+            match serde_json::from_str(line).map_err(|error| parse_error(number, error))? {
+                Record::Game(_) => return Err(LogError::Parse { line: number + 1, reason: "a second game record".to_string() }),
+                Record::Action(step) => replay.steps.push(step),
+                Record::Reward(reward) => replay.rewards.push((replay.steps.len(), reward)),
+                Record::End(end) => replay.end = Some(end)
+            }
+        }
+
+        Ok(replay)
+    }
+
+    // Before the first step
+    pub fn start(&self) -> GameState {
+        GameState::new(self.header.tiles.clone(), self.header.decks.clone(), self.header.players.len(), self.header.settings.clone())
+    }
+
+    // The game once the first `steps` were applied
+    pub fn state_at(&self, steps: usize) -> Result<GameState, LogError> {
+        let mut state = self.start();
+        for step in 0..steps.min(self.steps.len()) {
+            self.apply(&mut state, step)?;
+        }
+        Ok(state)
+    }
+
+    // Applies one step to a state built from this replay, which has to be at that step already
+    pub fn apply(&self, state: &mut GameState, step: usize) -> Result<(), LogError> {
+        let recorded = &self.steps[step];
+        match state.apply(recorded.seat, recorded.action.clone()) {
+            Ok(events) if events == recorded.events => Ok(()),
+            _ => Err(LogError::Diverged { step })
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests { // the snapshot tests play games the same way
+    use super::*;
+    use crate::{card, policy, tile};
+
+    pub(crate) fn header(seed: u64) -> Header {
+        Header {
+            version: VERSION,
+            settings: Settings { seed, turn_limit: 40, ..Settings::default() },
+            players: vec!["first".to_string(), "second".to_string(), "third".to_string()],
+            board: None,
+            tiles: tile::generate(4, 10),
+            decks: card::decks()
+        }
+    }
+
+    pub(crate) fn start(header: &Header) -> GameState {
+        GameState::new(header.tiles.clone(), header.decks.clone(), header.players.len(), header.settings.clone())
+    }
+
+    // the heuristic bot for every seat and nobody bidding, logging each step the way the board does
+    pub(crate) fn play(game: &mut GameState, log: &mut Log, steps: usize) {
+        for _ in 0..steps {
+            if game.finished { return; }
+
+            let (seat, action) = match &game.auction {
+                Some(auction) => (auction.bidders[0], Action::PassBid),
+                None => {
+                    let seat = game.raising.unwrap_or(game.current);
+                    (seat, policy::heuristic(game, seat).expect("The bot always has something to do"))
+                }
+            };
+            let events = game.apply(seat, action.clone()).expect("The bot only picks available actions");
+            log.write(&Record::Action(Step { seat, action, events })).expect("Writing the log");
+        }
+    }
+
+    pub(crate) fn scratch(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("monai-{}-{}", std::process::id(), name))
+    }
+
+    pub(crate) fn same(first: &GameState, second: &GameState) -> bool { // the rng doesn't compare, its serialized position does
+        serde_json::to_value(first).expect("Serializing") == serde_json::to_value(second).expect("Serializing")
+    }
+
+    #[test]
+    fn logged_game_replays_to_the_same_state() {
+        let path = scratch("replay.jsonl");
+        let header = header(3);
+        let mut game = start(&header);
+        let mut log = Log::create(&path, header.clone()).expect("Creating the log");
+
+        play(&mut game, &mut log, 400);
+        log.write(&Record::Reward(Reward { seat: 1, reward: 0.5 })).expect("Writing the log");
+        let end = End { winner: None, turns: game.turns, standings: game.standings() };
+        log.write(&Record::End(end.clone())).expect("Writing the log");
+
+        let replay = Replay::load(&path).expect("Loading the log");
+        assert_eq!(replay.header, header);
+        assert_eq!(replay.rewards, vec![(replay.steps.len(), Reward { seat: 1, reward: 0.5 })]);
+        assert_eq!(replay.end, Some(end));
+        assert!(same(&replay.state_at(replay.steps.len()).expect("Replaying"), &game));
+        assert_eq!(replay.state_at(0).expect("Replaying").turns, 0);
+
+        fs::remove_file(path).expect("Cleaning up");
+    }
+
+    #[test]
+    fn changed_events_are_caught() {
+        let path = scratch("diverged.jsonl");
+        let header = header(5);
+        let mut log = Log::create(&path, header.clone()).expect("Creating the log");
+        play(&mut start(&header), &mut log, 20);
+
+        let mut replay = Replay::load(&path).expect("Loading the log");
+        replay.steps[4].events.clear();
+        assert_eq!(replay.state_at(20).map(|_| ()), Err(LogError::Diverged { step: 4 }));
+
+        fs::remove_file(path).expect("Cleaning up");
+    }
+
+    #[test]
+    fn other_versions_and_headerless_logs_are_refused() {
+        let mut value = serde_json::to_value(Record::Game(header(1))).expect("Serializing");
+        value["version"] = (VERSION + 1).into();
+
+        assert_eq!(Replay::parse(&value.to_string()), Err(LogError::Version(VERSION + 1)));
+        assert_eq!(Replay::parse(""), Err(LogError::Header));
+        assert_eq!(Replay::parse(r#"{"record":"reward","seat":0,"reward":1.0}"#), Err(LogError::Header));
+        assert!(matches!(Replay::parse("not json"), Err(LogError::Parse { line: 1, .. })));
+    }
+}
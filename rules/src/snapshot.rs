@@ -0,0 +1,133 @@
+use std::{fmt, fs, io, path::{Path, PathBuf}};
+use serde::{Serialize, Deserialize};
+use crate::{GameState, replay::Header};
+
+pub const VERSION: u32 = 2; // bump whenever the game state changes shape, older snapshots are refused instead of misread
+
+// Everything needed to carry on a game after the board restarts
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub code: String, // the room, so players rejoin with the code they already have
+    pub corners: usize, // the polygon the tiles were laid out on
+    pub header: Header, // how the game started, player names by seat are matched against whoever reconnects
+    pub game: GameState,
+    pub log: Option<LogPosition>
+}
+
+// The game log as it was when the snapshot was taken, anything logged after it is cut off on restore
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LogPosition {
+    pub path: PathBuf,
+    pub length: u64
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SnapshotError {
+    Io(String),
+    Parse(String),
+    Version(u32)
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "could not read snapshot: {}", error),
+            SnapshotError::Parse(reason) => write!(f, "could not parse snapshot: {}", reason),
+            SnapshotError::Version(version) => write!(f, "snapshot is version {}, only version {} can be read", version, VERSION)
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl Snapshot {
+    // written next to the target and renamed over it, so a crash mid-save leaves the last snapshot intact
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+
+        let partial = path.with_extension("partial");
+        fs::write(&partial, serde_json::to_vec(self)?)?;
+        fs::rename(partial, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| SnapshotError::Io(format!("{}: {}", path.display(), error)))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SnapshotError> {
+        // like logs, the version is checked before the rest has to parse
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|error| SnapshotError::Parse(error.to_string()))?;
+        let version = value["version"].as_u64().ok_or(SnapshotError::Parse("missing version".to_string()))? as u32;
+        if version != VERSION { return Err(SnapshotError::Version(version)); }
+
+        serde_json::from_value(value).map_err(|error| SnapshotError::Parse(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::{Log, Replay, tests::{header, start, play, scratch, same}};
+
+    fn snapshot(game: &GameState, header: &Header, log: &Log) -> Snapshot {
+        Snapshot {
+            version: VERSION,
+            code: "BAKUDE".to_string(),
+            corners: 4,
+            header: header.clone(),
+            game: game.clone(),
+            log: Some(LogPosition { path: log.path().to_path_buf(), length: log.length() })
+        }
+    }
+
+    #[test]
+    fn restored_game_carries_on_the_same() {
+        let (path, saved) = (scratch("restored.jsonl"), scratch("restored.json"));
+        let header = header(9);
+        let mut game = start(&header);
+        let mut log = Log::create(&path, header.clone()).expect("Creating the log");
+        play(&mut game, &mut log, 60);
+        assert!(!game.finished);
+        snapshot(&game, &header, &log).save(&saved).expect("Saving the snapshot");
+
+        // the board carries on and crashes, everything after the snapshot is lost
+        play(&mut game, &mut log, 60);
+        drop(log);
+
+        let restored = Snapshot::load(&saved).expect("Loading the snapshot");
+        assert_eq!(restored.header, header);
+        let position = restored.log.expect("Snapshot has a log");
+        let mut resumed = Log::resume(&position.path, position.length).expect("Resuming the log");
+        let mut again = restored.game;
+        play(&mut again, &mut resumed, 60);
+        assert!(same(&again, &game));
+
+        let replay = Replay::load(&path).expect("Loading the resumed log");
+        assert!(same(&replay.state_at(replay.steps.len()).expect("Replaying"), &game));
+
+        fs::remove_file(path).expect("Cleaning up");
+        fs::remove_file(saved).expect("Cleaning up");
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let path = scratch("version.jsonl");
+        let header = header(2);
+        let log = Log::create(&path, header.clone()).expect("Creating the log");
+        let mut value = serde_json::to_value(snapshot(&start(&header), &header, &log)).expect("Serializing");
+
+        assert!(Snapshot::parse(&value.to_string()).is_ok());
+        value["version"] = (VERSION + 1).into();
+        assert_eq!(Snapshot::parse(&value.to_string()).map(|_| ()), Err(SnapshotError::Version(VERSION + 1)));
+        value.as_object_mut().expect("Snapshot is an object").remove("version");
+        assert!(matches!(Snapshot::parse(&value.to_string()), Err(SnapshotError::Parse(_))));
+
+        fs::remove_file(path).expect("Cleaning up");
+    }
+}
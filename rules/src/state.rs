@@ -0,0 +1,1592 @@
+use serde::{Serialize, Deserialize};
+use crate::{action::{Action, Event, Rejection}, auction::Auction, card::{Deck, Effect}, dice::{self, Random}, player::PlayerState, tile::{TileState, TileKind, Levy, Tier, HOTEL}, trade::{Offer, Trade}};
+
+pub const STARTING_MONEY: i32 = 1000;
+pub const GO_BONUS: i32 = 200;
+pub const SELL_RATE: f32 = 0.8; // arbitrary
+pub const HOUSE_SELL_RATE: f32 = 0.5;
+pub const MORTGAGE_RATE: f32 = 0.5;
+pub const MORTGAGE_INTEREST: f32 = 0.1;
+pub const TURN_LIMIT: usize = 100;
+pub const BAIL: i32 = 50;
+pub const JAIL_ROLLS: usize = 3; // failed attempts at doubles before bail is forced
+pub const DOUBLES_LIMIT: usize = 3; // consecutive doubles before going to jail
+
+// House rules and amounts a board can change per game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    pub starting_money: i32,
+    pub go_salary: i32,
+    pub turn_limit: usize, // ends in a stalemate, ranked by net worth. Counts turns, not rolls
+    pub jackpot: bool, // taxes and fines pile up on free parking instead of going to the bank
+    pub seed: u64 // deck shuffles and dice, the same seed and actions play the same game
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            starting_money: STARTING_MONEY,
+            go_salary: GO_BONUS,
+            turn_limit: TURN_LIMIT,
+            jackpot: false,
+            seed: rand::random()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameState {
+    pub settings: Settings,
+    pub tiles: Vec<TileState>,
+    pub decks: Vec<Deck>,
+    pub players: Vec<PlayerState>,
+    pub current: usize,
+    pub turns: usize, // rolling again after doubles is still the same turn
+    pub rolled: bool,
+    pub doubles: usize, // consecutive doubles this turn, nonzero means another roll after ending
+    pub last_roll: usize, // dice total, utility rent multiplies this
+    pub auction: Option<Auction>,
+    pub trades: Vec<Trade>, // pending proposals
+    pub raising: Option<usize>, // player selling off assets before their turn can end
+    pub next_trade: usize,
+    pub jackpot: i32, // waiting on free parking
+    pub random: Random, // seeded from settings.seed
+    pub finished: bool
+}
+
+impl GameState {
+    pub fn new(tiles: Vec<TileState>, mut decks: Vec<Deck>, players: usize, settings: Settings) -> Self {
+        let mut random = Random::new(settings.seed);
+        for deck in decks.iter_mut() {
+            deck.shuffle(&mut random);
+        }
+
+        Self {
+            players: (0..players).map(|_| PlayerState::new(settings.starting_money)).collect(),
+            settings,
+            tiles,
+            decks,
+            current: 0,
+            turns: 0,
+            rolled: false,
+            doubles: 0,
+            last_roll: 0,
+            auction: None,
+            trades: vec![],
+            next_trade: 0,
+            raising: None,
+            jackpot: 0,
+            random,
+            finished: false
+        }
+    }
+
+    pub fn apply(&mut self, player: usize, action: Action) -> Result<Vec<Event>, Rejection> {
+        if self.finished { return Err(Rejection::GameFinished); }
+        if player >= self.players.len() || self.players[player].bankrupt { return Err(Rejection::UnknownPlayer); }
+        if self.auction.is_some() && !matches!(action, Action::Bid(_) | Action::PassBid | Action::Forfeit) {
+            return Err(Rejection::AuctionInProgress);
+        }
+        if let Some(debtor) = self.raising {
+            let allowed = if player == debtor {
+                matches!(action, Action::Sell(_) | Action::SellHouse(_) | Action::Mortgage(_) | Action::DeclareBankruptcy | Action::Forfeit)
+            } else {
+                matches!(action, Action::RejectTrade(_) | Action::Forfeit)
+            };
+
+            if !allowed { return Err(Rejection::RaisingFunds); }
+        }
+
+        let anytime = matches!(action, Action::Bid(_) | Action::PassBid | Action::Forfeit
+            | Action::ProposeTrade { .. } | Action::AcceptTrade(_) | Action::RejectTrade(_) | Action::CounterTrade { .. });
+        if !anytime && player != self.current { return Err(Rejection::NotYourTurn); }
+
+        let raising = self.raising == Some(player);
+        let mut events = vec![];
+        match action {
+            Action::Roll => self.roll(player, &mut events)?,
+            Action::PayBail => self.pay_bail(player, &mut events)?,
+            Action::UseJailCard => self.use_jail_card(player, &mut events)?,
+            Action::Buy => self.buy(player, &mut events)?,
+            Action::Sell(tile) => self.sell(player, tile, &mut events)?,
+            Action::Build(tile) => self.build(player, tile, &mut events)?,
+            Action::SellHouse(tile) => self.sell_house(player, tile, &mut events)?,
+            Action::Mortgage(tile) => self.mortgage(player, tile, &mut events)?,
+            Action::Unmortgage(tile) => self.unmortgage(player, tile, &mut events)?,
+            Action::Bid(amount) => self.bid(player, amount, &mut events)?,
+            Action::PassBid => self.pass_bid(player, &mut events)?,
+            Action::ProposeTrade { to, give, take } => self.propose_trade(player, to, give, take, &mut events)?,
+            Action::AcceptTrade(trade) => self.accept_trade(player, trade, &mut events)?,
+            Action::RejectTrade(trade) => self.reject_trade(player, trade, &mut events)?,
+            Action::CounterTrade { trade, give, take } => self.counter_trade(player, trade, give, take, &mut events)?,
+            Action::EndTurn => self.end_turn(player, &mut events)?,
+            Action::DeclareBankruptcy => self.declare_bankruptcy(player, &mut events)?,
+            Action::Forfeit => self.forfeit(player, &mut events)
+        }
+
+        if raising && self.raising == Some(player) { self.check_debt(player, &mut events)?; }
+
+        Ok(events)
+    }
+
+    // What the player could reasonably do right now, either the jail choices before rolling or EndTurn first
+    // During an auction only bidding is possible, Bid carries the minimum raise
+    // While raising funds the debtor can only sell, mortgage or give up
+    pub fn available_actions(&self, player: usize) -> Vec<Action> {
+        let mut actions = vec![];
+        let state = &self.players[player];
+
+        if let Some(auction) = &self.auction {
+            if auction.bidders.contains(&player) {
+                if state.money > auction.highest { actions.push(Action::Bid(auction.highest + 1)); }
+                if auction.leader != Some(player) { actions.push(Action::PassBid); }
+            }
+
+            return actions;
+        }
+
+        if let Some(debtor) = self.raising {
+            if debtor != player { return actions; }
+
+            for (index, tile) in self.tiles.iter().enumerate() {
+                if tile.owner != Some(player) { continue; }
+
+                if !self.group_has_houses(index) { actions.push(Action::Sell(index)); }
+                if self.can_sell_house(player, index).is_ok() { actions.push(Action::SellHouse(index)); }
+                if self.can_mortgage(player, index).is_ok() { actions.push(Action::Mortgage(index)); }
+            }
+            actions.push(Action::DeclareBankruptcy);
+
+            return actions;
+        }
+
+        if player != self.current { return actions; } // trades and forfeiting are always possible but never offered
+
+        if !self.rolled {
+            actions.push(Action::Roll);
+            if state.jailed.is_some() && state.money >= BAIL { actions.push(Action::PayBail); }
+            if state.jailed.is_some() && !state.jail_cards.is_empty() { actions.push(Action::UseJailCard); }
+        } else {
+            actions.push(Action::EndTurn);
+        }
+
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if tile.owner != Some(player) { continue; }
+
+            if !self.group_has_houses(index) { actions.push(Action::Sell(index)); }
+            if self.can_build(player, index).is_ok() { actions.push(Action::Build(index)); }
+            if self.can_sell_house(player, index).is_ok() { actions.push(Action::SellHouse(index)); }
+            if self.can_mortgage(player, index).is_ok() { actions.push(Action::Mortgage(index)); }
+            if self.can_unmortgage(player, index).is_ok() { actions.push(Action::Unmortgage(index)); }
+        }
+
+        let tile = &self.tiles[state.position];
+        if self.rolled && state.money >= tile.cost && tile.is_ownable() && tile.owner.is_none() { actions.push(Action::Buy); }
+
+        actions
+    }
+
+    pub fn solvent_players(&self) -> Vec<usize> {
+        (0..self.players.len()).filter(|player| !self.players[*player].bankrupt).collect()
+    }
+
+    // Solvent players and their net worth, richest first
+    pub fn standings(&self) -> Vec<(usize, i32)> {
+        let mut standings: Vec<(usize, i32)> = self.solvent_players().into_iter().map(|player| (player, self.net_worth(player))).collect();
+        standings.sort_by_key(|(_, worth)| std::cmp::Reverse(*worth)); // stable, so seats stay in order on ties
+        standings
+    }
+
+    // Where GoToJail sends players
+    pub fn jail(&self) -> usize {
+        self.tiles.iter().position(|tile| tile.kind == TileKind::Jail).unwrap_or(0)
+    }
+
+    // Money plus what everything owned cost, mortgages and houses included
+    pub fn net_worth(&self, player: usize) -> i32 {
+        let assets: i32 = self.tiles.iter().enumerate()
+            .filter(|(_, tile)| tile.owner == Some(player))
+            .map(|(index, tile)| {
+                let cost = if tile.mortgaged { tile.cost - self.mortgage_value(index) } else { tile.cost };
+                cost + tile.house_cost * tile.houses as i32
+            })
+            .sum();
+
+        self.players[player].money + assets
+    }
+
+    pub fn tax(&self, player: usize, levy: Levy) -> i32 {
+        match levy {
+            Levy::Fixed(amount) => amount,
+            Levy::Percent(percent) => (self.net_worth(player).max(0) as f32 * percent as f32 / 100.0).ceil() as i32
+        }
+    }
+
+    pub fn rent(&self, tile: usize) -> i32 {
+        let state = &self.tiles[tile];
+        let set = |owner: Option<usize>| owner.map_or(1, |owner| self.count_owned(owner, state.kind)).clamp(1, HOTEL + 1) - 1;
+
+        match (state.kind, state.owner) {
+            (TileKind::Railroad, owner) => state.rent[set(owner)],
+            (TileKind::Utility, owner) => state.rent[set(owner)] * self.last_roll as i32,
+            (_, Some(owner)) if state.houses == 0 && self.owns_group(owner, tile) => state.rent[0] * 2,
+            _ => state.rent[state.houses]
+        }
+    }
+
+    // How many tiles of this kind the player owns, mortgaged ones included
+    pub fn count_owned(&self, player: usize, kind: TileKind) -> usize {
+        self.tiles.iter().filter(|tile| tile.kind == kind && tile.owner == Some(player)).count()
+    }
+
+    // Every tile sharing a group with this one, including itself
+    pub fn group_of(&self, tile: usize) -> Vec<usize> {
+        match self.tiles[tile].group {
+            Some(group) => (0..self.tiles.len()).filter(|index| self.tiles[*index].group == Some(group)).collect(),
+            None => vec![tile]
+        }
+    }
+
+    pub fn owns_group(&self, player: usize, tile: usize) -> bool {
+        self.tiles[tile].group.is_some()
+            && self.group_of(tile).iter().all(|index| self.tiles[*index].owner == Some(player))
+    }
+
+    pub fn group_has_houses(&self, tile: usize) -> bool {
+        self.group_of(tile).iter().any(|index| self.tiles[*index].houses > 0)
+    }
+
+    pub fn can_build(&self, player: usize, tile: usize) -> Result<(), Rejection> {
+        let state = self.tiles.get(tile).ok_or(Rejection::UnknownTile)?;
+
+        if state.owner != Some(player) { return Err(Rejection::NotOwner); }
+        if state.kind != TileKind::Property || state.house_cost == 0 { return Err(Rejection::NotBuildable); }
+        if !self.owns_group(player, tile) { return Err(Rejection::NoMonopoly); }
+        if self.group_of(tile).iter().any(|index| self.tiles[*index].mortgaged) { return Err(Rejection::Mortgaged); }
+        if state.houses >= HOTEL { return Err(Rejection::FullyBuilt); }
+
+        let fewest = self.group_of(tile).iter().map(|index| self.tiles[*index].houses).min().unwrap_or(0);
+        if state.houses > fewest { return Err(Rejection::UnevenBuilding); }
+        if self.players[player].money < state.house_cost { return Err(Rejection::InsufficientFunds); }
+
+        Ok(())
+    }
+
+    pub fn can_sell_house(&self, player: usize, tile: usize) -> Result<(), Rejection> {
+        let state = self.tiles.get(tile).ok_or(Rejection::UnknownTile)?;
+
+        if state.owner != Some(player) { return Err(Rejection::NotOwner); }
+        if state.houses == 0 { return Err(Rejection::NoHouses); }
+
+        let most = self.group_of(tile).iter().map(|index| self.tiles[*index].houses).max().unwrap_or(0);
+        if state.houses < most { return Err(Rejection::UnevenBuilding); }
+
+        Ok(())
+    }
+
+    pub fn mortgage_value(&self, tile: usize) -> i32 {
+        (self.tiles[tile].cost as f32 * MORTGAGE_RATE).ceil() as i32
+    }
+
+    pub fn unmortgage_cost(&self, tile: usize) -> i32 { // principal plus interest
+        (self.mortgage_value(tile) as f32 * (1.0 + MORTGAGE_INTEREST)).ceil() as i32
+    }
+
+    pub fn can_mortgage(&self, player: usize, tile: usize) -> Result<(), Rejection> {
+        let state = self.tiles.get(tile).ok_or(Rejection::UnknownTile)?;
+
+        if state.owner != Some(player) { return Err(Rejection::NotOwner); }
+        if state.mortgaged { return Err(Rejection::Mortgaged); }
+        if self.group_has_houses(tile) { return Err(Rejection::HasBuildings); }
+
+        Ok(())
+    }
+
+    pub fn can_unmortgage(&self, player: usize, tile: usize) -> Result<(), Rejection> {
+        let state = self.tiles.get(tile).ok_or(Rejection::UnknownTile)?;
+
+        if state.owner != Some(player) { return Err(Rejection::NotOwner); }
+        if !state.mortgaged { return Err(Rejection::NotMortgaged); }
+        if self.players[player].money < self.unmortgage_cost(tile) { return Err(Rejection::InsufficientFunds); }
+
+        Ok(())
+    }
+
+    pub fn can_raise(&self, player: usize) -> bool { // anything left to sell
+        self.tiles.iter().any(|tile| tile.owner == Some(player))
+    }
+
+    pub fn can_give(&self, player: usize, offer: &Offer) -> Result<(), Rejection> {
+        let state = &self.players[player];
+        if offer.money < 0 { return Err(Rejection::InvalidTrade); }
+        if offer.money > state.money { return Err(Rejection::InsufficientFunds); }
+        if offer.jail_cards > state.jail_cards.len() { return Err(Rejection::NoJailCard); }
+
+        for (index, tile) in offer.tiles.iter().enumerate() {
+            if *tile >= self.tiles.len() { return Err(Rejection::UnknownTile); }
+            if offer.tiles[..index].contains(tile) { return Err(Rejection::InvalidTrade); }
+            if self.tiles[*tile].owner != Some(player) { return Err(Rejection::NotOwner); }
+            if self.group_has_houses(*tile) { return Err(Rejection::HasBuildings); } // houses are sold before trading
+        }
+
+        Ok(())
+    }
+
+    // Checked when proposing and again when accepting, since either side may have spent what they offered
+    pub fn can_trade(&self, trade: &Trade) -> Result<(), Rejection> {
+        if trade.to >= self.players.len() || self.players[trade.to].bankrupt { return Err(Rejection::UnknownPlayer); }
+        if trade.from == trade.to || (trade.give.is_empty() && trade.take.is_empty()) { return Err(Rejection::InvalidTrade); }
+
+        self.can_give(trade.from, &trade.give)?;
+        self.can_give(trade.to, &trade.take)
+    }
+
+    fn roll(&mut self, player: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        if player != self.current { return Err(Rejection::NotYourTurn); }
+        if self.rolled { return Err(Rejection::AlreadyRolled); }
+
+        let roll = dice::roll(&mut self.random);
+        self.rolled = true;
+        self.last_roll = roll.total();
+        events.push(Event::Rolled { player, roll });
+
+        if let Some(attempts) = self.players[player].jailed {
+            if roll.is_double() {
+                self.release(player, events);
+            } else if attempts + 1 >= JAIL_ROLLS {
+                self.fine(player, BAIL, events); // forced, even into debt
+                self.release(player, events);
+            } else {
+                self.players[player].jailed = Some(attempts + 1);
+                return Ok(());
+            }
+        } else if roll.is_double() {
+            self.doubles += 1;
+            if self.doubles >= DOUBLES_LIMIT {
+                self.send_to_jail(player, events);
+                return Ok(());
+            }
+        } else {
+            self.doubles = 0;
+        }
+
+        self.advance(player, roll.total(), events);
+        self.land(player, events);
+
+        Ok(())
+    }
+
+    fn pay_bail(&mut self, player: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        if player != self.current { return Err(Rejection::NotYourTurn); }
+        if self.rolled { return Err(Rejection::AlreadyRolled); }
+        if self.players[player].jailed.is_none() { return Err(Rejection::NotInJail); }
+        if self.players[player].money < BAIL { return Err(Rejection::InsufficientFunds); }
+
+        self.fine(player, BAIL, events);
+        self.release(player, events);
+
+        Ok(())
+    }
+
+    fn use_jail_card(&mut self, player: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        if player != self.current { return Err(Rejection::NotYourTurn); }
+        if self.rolled { return Err(Rejection::AlreadyRolled); }
+        if self.players[player].jailed.is_none() { return Err(Rejection::NotInJail); }
+
+        let (deck, card) = self.players[player].jail_cards.pop().ok_or(Rejection::NoJailCard)?;
+        self.decks[deck].put_back(card);
+        self.release(player, events);
+
+        Ok(())
+    }
+
+    fn send_to_jail(&mut self, player: usize, events: &mut Vec<Event>) {
+        let jail = self.jail();
+        self.move_to(player, jail, false, events);
+
+        self.players[player].jailed = Some(0);
+        self.doubles = 0; // no extra roll once jailed
+        events.push(Event::Jailed { player });
+    }
+
+    fn release(&mut self, player: usize, events: &mut Vec<Event>) {
+        self.players[player].jailed = None;
+        events.push(Event::LeftJail { player });
+    }
+
+    fn advance(&mut self, player: usize, steps: usize, events: &mut Vec<Event>) {
+        let position = self.players[player].position + steps;
+        self.move_to(player, position % self.tiles.len(), position >= self.tiles.len(), events);
+    }
+
+    fn move_to(&mut self, player: usize, tile: usize, passes_go: bool, events: &mut Vec<Event>) {
+        let state = &mut self.players[player];
+
+        if passes_go {
+            state.money += self.settings.go_salary;
+            events.push(Event::PassedGo { player, amount: self.settings.go_salary });
+        }
+
+        state.position = tile;
+        events.push(Event::Moved { player, tile });
+    }
+
+    fn land(&mut self, player: usize, events: &mut Vec<Event>) {
+        let tile = self.players[player].position;
+
+        match self.tiles[tile].kind {
+            TileKind::Chance(deck) => self.draw(player, deck, events),
+            TileKind::Tax(levy) => {
+                let amount = self.tax(player, levy);
+                self.fine(player, amount, events);
+            }
+            TileKind::FreeParking if self.settings.jackpot && self.jackpot > 0 => {
+                let amount = self.jackpot;
+                self.jackpot = 0;
+                self.transfer(None, Some(player), amount, events);
+            }
+            TileKind::GoToJail => self.send_to_jail(player, events),
+            _ => self.pay_rent(player, tile, events)
+        }
+    }
+
+    fn pay_rent(&mut self, player: usize, tile: usize, events: &mut Vec<Event>) {
+        if let Some(owner) = self.tiles[tile].owner {
+            if owner == player || self.tiles[tile].mortgaged { return; }
+
+            let amount = self.rent(tile);
+            self.charge(player, Some(owner), amount);
+            self.players[owner].money += amount;
+            events.push(Event::PaidRent { player, owner, tile, amount });
+        }
+    }
+
+    fn draw(&mut self, player: usize, deck: usize, events: &mut Vec<Event>) {
+        let card = match self.decks.get_mut(deck).and_then(|deck| deck.draw()) {
+            Some(card) => card,
+            None => return // empty deck, every card is held
+        };
+        events.push(Event::DrewCard { player, deck, card });
+
+        let effect = self.decks[deck].cards[card].effect;
+        if effect == Effect::GetOutOfJail {
+            self.players[player].jail_cards.push((deck, card));
+        } else {
+            self.decks[deck].put_back(card);
+        }
+
+        let position = self.players[player].position;
+        match effect {
+            Effect::Advance(tile) => {
+                self.move_to(player, tile % self.tiles.len(), tile <= position, events);
+                self.land(player, events);
+            }
+            Effect::Back(spaces) => {
+                let tile = (position + self.tiles.len() - spaces % self.tiles.len()) % self.tiles.len();
+                self.move_to(player, tile, false, events);
+                self.land(player, events);
+            }
+            Effect::Collect(amount) => self.transfer(None, Some(player), amount, events),
+            Effect::Pay(amount) => self.fine(player, amount, events),
+            Effect::CollectFromEach(amount) => {
+                for other in self.solvent_players().into_iter().filter(|other| *other != player) {
+                    self.transfer(Some(other), Some(player), amount, events);
+                }
+            }
+            Effect::PayEach(amount) => {
+                for other in self.solvent_players().into_iter().filter(|other| *other != player) {
+                    self.transfer(Some(player), Some(other), amount, events);
+                }
+            }
+            Effect::GoToJail => self.send_to_jail(player, events),
+            Effect::GetOutOfJail => {}
+            Effect::Repairs { house, hotel } => {
+                let amount = self.tiles.iter()
+                    .filter(|tile| tile.owner == Some(player))
+                    .map(|tile| if tile.houses == HOTEL { hotel } else { house * tile.houses as i32 })
+                    .sum();
+
+                if amount > 0 { self.fine(player, amount, events); }
+            }
+        }
+    }
+
+    fn transfer(&mut self, from: Option<usize>, to: Option<usize>, amount: i32, events: &mut Vec<Event>) {
+        if let Some(from) = from { self.charge(from, to, amount); }
+        if let Some(to) = to { self.players[to].money += amount; }
+
+        events.push(Event::Payment { from, to, amount });
+    }
+
+    fn fine(&mut self, player: usize, amount: i32, events: &mut Vec<Event>) { // paid to the bank, or the jackpot
+        self.transfer(Some(player), None, amount, events);
+        if self.settings.jackpot { self.jackpot += amount; }
+    }
+
+    fn charge(&mut self, player: usize, creditor: Option<usize>, amount: i32) {
+        let state = &mut self.players[player];
+        if state.money >= 0 { state.debts.clear(); } // anything owed before was settled
+
+        state.money -= amount;
+        let short = amount.min(-state.money);
+        if short > 0 { state.debts.push((creditor, short)); }
+    }
+
+    fn buy(&mut self, player: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        let index = self.players[player].position;
+        let tile = &mut self.tiles[index];
+
+        if !self.rolled { return Err(Rejection::NotRolled); }
+        if !tile.is_ownable() { return Err(Rejection::NotOwnable); }
+        if tile.owner.is_some() { return Err(Rejection::AlreadyOwned); }
+        if self.players[player].money < tile.cost { return Err(Rejection::InsufficientFunds); }
+
+        tile.owner = Some(player);
+        tile.tier = Tier::Owned;
+        let price = tile.cost;
+        self.charge(player, None, price);
+        events.push(Event::Bought { player, tile: index, price });
+
+        Ok(())
+    }
+
+    fn sell(&mut self, player: usize, index: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        if self.tiles.get(index).ok_or(Rejection::UnknownTile)?.owner != Some(player) { return Err(Rejection::NotOwner); }
+        if self.group_has_houses(index) { return Err(Rejection::HasBuildings); }
+
+        let mut price = (self.tiles[index].cost as f32 * SELL_RATE).ceil() as i32;
+        if self.tiles[index].mortgaged { price -= self.mortgage_value(index); } // the bank keeps its principal
+
+        let tile = &mut self.tiles[index];
+        tile.owner = None;
+        tile.mortgaged = false;
+        tile.tier = Tier::None;
+        self.players[player].money += price;
+        events.push(Event::Sold { player, tile: index, price });
+
+        Ok(())
+    }
+
+    fn build(&mut self, player: usize, index: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        self.can_build(player, index)?;
+
+        let tile = &mut self.tiles[index];
+        let houses = tile.houses + 1;
+        tile.set_houses(houses);
+        self.players[player].money -= tile.house_cost;
+        events.push(Event::Built { player, tile: index, houses, price: tile.house_cost });
+
+        Ok(())
+    }
+
+    fn sell_house(&mut self, player: usize, index: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        self.can_sell_house(player, index)?;
+
+        let tile = &mut self.tiles[index];
+        let houses = tile.houses - 1;
+        let price = (tile.house_cost as f32 * HOUSE_SELL_RATE).ceil() as i32;
+        tile.set_houses(houses);
+        self.players[player].money += price;
+        events.push(Event::SoldHouse { player, tile: index, houses, price });
+
+        Ok(())
+    }
+
+    fn mortgage(&mut self, player: usize, index: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        self.can_mortgage(player, index)?;
+
+        let amount = self.mortgage_value(index);
+        self.tiles[index].mortgaged = true;
+        self.players[player].money += amount;
+        events.push(Event::Mortgaged { player, tile: index, amount });
+
+        Ok(())
+    }
+
+    fn unmortgage(&mut self, player: usize, index: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        self.can_unmortgage(player, index)?;
+
+        let amount = self.unmortgage_cost(index);
+        self.tiles[index].mortgaged = false;
+        self.players[player].money -= amount;
+        events.push(Event::Unmortgaged { player, tile: index, amount });
+
+        Ok(())
+    }
+
+    fn end_turn(&mut self, player: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        if player != self.current { return Err(Rejection::NotYourTurn); }
+        if !self.rolled { return Err(Rejection::NotRolled); }
+
+        if self.players[player].money < 0 && self.can_raise(player) { // the turn finishes once the debt is settled
+            self.raising = Some(player);
+            events.push(Event::RaisingFunds { player, debt: -self.players[player].money });
+            return Ok(());
+        }
+
+        let position = self.players[player].position;
+        if self.tiles[position].is_ownable() && self.tiles[position].owner.is_none() {
+            let bidders: Vec<usize> = self.solvent_players().into_iter().filter(|bidder| self.players[*bidder].money > 0).collect();
+
+            if !bidders.is_empty() {
+                self.auction = Some(Auction::new(position, bidders));
+                events.push(Event::AuctionStarted { tile: position });
+                return Ok(()); // the turn finishes when the auction settles
+            }
+        }
+
+        self.finish_turn(player, events);
+        Ok(())
+    }
+
+    fn finish_turn(&mut self, player: usize, events: &mut Vec<Event>) {
+        events.push(Event::TurnEnded { player });
+        if !self.players[player].bankrupt && self.players[player].money < 0 {
+            self.bankrupt(player, self.players[player].creditor(), events);
+        }
+
+        self.pass_turn(events);
+    }
+
+    fn bid(&mut self, player: usize, amount: i32, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        let auction = self.auction.as_mut().ok_or(Rejection::NoAuction)?;
+
+        if !auction.bidders.contains(&player) { return Err(Rejection::NotBidding); }
+        if amount <= auction.highest { return Err(Rejection::BidTooLow); }
+        if amount > self.players[player].money { return Err(Rejection::InsufficientFunds); }
+
+        auction.leader = Some(player);
+        auction.highest = amount;
+        events.push(Event::BidPlaced { player, amount });
+
+        self.settle_auction(events);
+        Ok(())
+    }
+
+    fn pass_bid(&mut self, player: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        let auction = self.auction.as_mut().ok_or(Rejection::NoAuction)?;
+
+        if !auction.bidders.contains(&player) { return Err(Rejection::NotBidding); }
+        if auction.leader == Some(player) { return Err(Rejection::LeadingBid); }
+
+        auction.withdraw(player);
+        events.push(Event::BidPassed { player });
+
+        self.settle_auction(events);
+        Ok(())
+    }
+
+    fn settle_auction(&mut self, events: &mut Vec<Event>) {
+        if !self.auction.as_ref().is_some_and(|auction| auction.is_settled()) { return; }
+        let auction = self.auction.take().expect("Settled auction disappeared");
+
+        if let Some(winner) = auction.leader {
+            let tile = &mut self.tiles[auction.tile];
+            tile.owner = Some(winner);
+            tile.tier = Tier::Owned;
+            self.players[winner].money -= auction.highest;
+        }
+        events.push(Event::AuctionEnded { tile: auction.tile, winner: auction.leader, price: auction.highest });
+
+        self.finish_turn(self.current, events);
+    }
+
+    fn propose_trade(&mut self, player: usize, to: usize, give: Offer, take: Offer, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        let trade = Trade { id: self.next_trade, from: player, to, give, take };
+        self.can_trade(&trade)?;
+
+        self.next_trade += 1;
+        events.push(Event::TradeProposed { trade: trade.id, from: trade.from, to: trade.to });
+        self.trades.push(trade);
+        Ok(())
+    }
+
+    fn find_trade(&self, player: usize, id: usize) -> Result<usize, Rejection> {
+        let index = self.trades.iter().position(|trade| trade.id == id).ok_or(Rejection::UnknownTrade)?;
+        if !self.trades[index].involves(player) { return Err(Rejection::NotTrading); }
+        Ok(index)
+    }
+
+    fn accept_trade(&mut self, player: usize, id: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        let index = self.find_trade(player, id)?;
+        if self.trades[index].to != player { return Err(Rejection::NotTrading); }
+        self.can_trade(&self.trades[index])?;
+
+        // everything was validated up front so both halves always go through together
+        let trade = self.trades.remove(index);
+        self.exchange(trade.from, trade.to, &trade.give, events);
+        self.exchange(trade.to, trade.from, &trade.take, events);
+        events.push(Event::TradeAccepted { trade: trade.id, from: trade.from, to: trade.to });
+        Ok(())
+    }
+
+    fn reject_trade(&mut self, player: usize, id: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        let index = self.find_trade(player, id)?;
+        let trade = self.trades.remove(index);
+
+        events.push(Event::TradeRejected { trade: trade.id, from: trade.from, to: trade.to });
+        Ok(())
+    }
+
+    fn counter_trade(&mut self, player: usize, id: usize, give: Offer, take: Offer, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        let index = self.find_trade(player, id)?;
+        if self.trades[index].to != player { return Err(Rejection::NotTrading); }
+        self.can_trade(&Trade { id: self.next_trade, from: player, to: self.trades[index].from, give: give.clone(), take: take.clone() })?;
+
+        let to = self.trades[index].from;
+        self.reject_trade(player, id, events)?;
+        self.propose_trade(player, to, give, take, events)
+    }
+
+    fn exchange(&mut self, from: usize, to: usize, offer: &Offer, events: &mut Vec<Event>) {
+        if offer.money > 0 { self.transfer(Some(from), Some(to), offer.money, events); }
+        for tile in offer.tiles.iter() {
+            self.tiles[*tile].owner = Some(to); // mortgages go with the tile
+        }
+
+        let kept = self.players[from].jail_cards.len() - offer.jail_cards;
+        let cards = self.players[from].jail_cards.split_off(kept);
+        self.players[to].jail_cards.extend(cards);
+    }
+
+    // After each sale while raising funds, either carry on ending the turn or give up once nothing is left
+    fn check_debt(&mut self, player: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        if self.players[player].money >= 0 {
+            self.raising = None;
+            events.push(Event::DebtSettled { player });
+            self.end_turn(player, events)
+        } else if !self.can_raise(player) {
+            self.raising = None;
+            self.finish_turn(player, events);
+            Ok(())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn declare_bankruptcy(&mut self, player: usize, events: &mut Vec<Event>) -> Result<(), Rejection> {
+        if self.raising != Some(player) { return Err(Rejection::NotInDebt); }
+
+        self.raising = None;
+        self.finish_turn(player, events);
+        Ok(())
+    }
+
+    fn forfeit(&mut self, player: usize, events: &mut Vec<Event>) {
+        if self.raising == Some(player) { // same as declaring, the creditor still gets paid
+            self.raising = None;
+            self.finish_turn(player, events);
+            return;
+        }
+
+        self.bankrupt(player, None, events);
+
+        if let Some(auction) = self.auction.as_mut() { // the turn carries on once the auction settles
+            auction.withdraw(player);
+            self.settle_auction(events);
+        } else if player == self.current {
+            self.pass_turn(events);
+        }
+    }
+
+    // Assets go to the creditor, or back to the bank. Houses are always returned to the bank, and debts past what
+    // the player had are written off
+    fn bankrupt(&mut self, player: usize, creditor: Option<usize>, events: &mut Vec<Event>) {
+        let creditor = creditor.filter(|creditor| *creditor != player && !self.players[*creditor].bankrupt);
+
+        // rent and payments were credited in full. Debts are paid off in the order they were run up, so what's still
+        // unpaid is the latest of them, players give back their part of it and the bank's is written off
+        let mut unpaid = -self.players[player].money.min(0);
+        for (owed, amount) in std::mem::take(&mut self.players[player].debts).into_iter().rev() {
+            let returned = amount.min(unpaid);
+            unpaid -= returned;
+
+            if let Some(owed) = owed.filter(|owed| returned > 0 && *owed != player && !self.players[*owed].bankrupt) {
+                self.charge(owed, None, returned);
+                self.players[player].money += returned;
+                events.push(Event::Payment { from: Some(owed), to: Some(player), amount: returned });
+            }
+        }
+
+        self.players[player].bankrupt = true;
+        let cards: Vec<(usize, usize)> = self.players[player].jail_cards.drain(..).collect();
+        match creditor {
+            Some(creditor) => self.players[creditor].jail_cards.extend(cards),
+            None => for (deck, card) in cards { self.decks[deck].put_back(card); }
+        }
+        for tile in self.tiles.iter_mut().filter(|tile| tile.owner == Some(player)) {
+            tile.owner = creditor;
+            if creditor.is_none() { tile.mortgaged = false; } // creditors take on the mortgage
+            tile.set_houses(0);
+        }
+        for trade in self.trades.iter().filter(|trade| trade.involves(player)) {
+            events.push(Event::TradeRejected { trade: trade.id, from: trade.from, to: trade.to });
+        }
+        self.trades.retain(|trade| !trade.involves(player));
+        events.push(Event::Bankrupt { player, creditor });
+
+        let solvent = self.solvent_players();
+        if solvent.len() <= 1 {
+            self.finished = true;
+            self.auction = None;
+            self.raising = None;
+            events.push(Event::GameOver { winner: solvent.first().copied() });
+        }
+    }
+
+    fn pass_turn(&mut self, events: &mut Vec<Event>) {
+        if self.finished { return; }
+
+        self.rolled = false;
+        if self.doubles > 0 && !self.players[self.current].bankrupt { return; } // same player rolls again, same turn
+
+        self.doubles = 0;
+        self.turns += 1;
+        if self.turns >= self.settings.turn_limit {
+            self.finished = true;
+            events.push(Event::TurnLimit { standings: self.standings() });
+            events.push(Event::GameOver { winner: None });
+            return;
+        }
+
+        loop {
+            self.current = (self.current + 1) % self.players.len();
+            if !self.players[self.current].bankrupt { break; }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{card, tile::TileState};
+
+    // GO, a two tile group, jail, a railroad, then a lone property, small enough to reason about by hand
+    fn board() -> Vec<TileState> {
+        vec![
+            TileState::new(TileKind::Go, None, 0),
+            TileState::property(0, 100, [10, 50, 150, 450, 625, 750], 50),
+            TileState::property(0, 120, [12, 60, 180, 500, 700, 900], 50),
+            TileState::new(TileKind::Jail, None, 0),
+            TileState::railroad(200, [25, 50, 100, 200, 200, 200]),
+            TileState::property(1, 150, [15, 75, 200, 550, 750, 950], 100)
+        ]
+    }
+
+    fn game(players: usize) -> GameState {
+        GameState::new(board(), card::decks(), players, Settings { seed: 7, ..Settings::default() })
+    }
+
+    // the dice are reseeded so the next roll is doubles or not, whatever seed the game started with
+    fn next_roll(game: &mut GameState, double: bool) {
+        let seed = (0..).find(|seed| dice::roll(&mut Random::new(*seed)).is_double() == double).expect("Some seed rolls it");
+        game.random = Random::new(seed);
+    }
+
+    // player 0 owns everything, so rolling around never pays rent or starts an auction
+    fn rolling() -> GameState {
+        let mut game = game(2);
+        for tile in game.tiles.iter_mut().filter(|tile| tile.is_ownable()) {
+            tile.owner = Some(0);
+        }
+        game
+    }
+
+    #[test]
+    fn doubles_roll_again_in_the_same_turn() {
+        let mut game = rolling();
+        game.settings.turn_limit = 2;
+
+        next_roll(&mut game, true);
+        game.apply(0, Action::Roll).expect("Rolling");
+        assert_eq!(game.doubles, 1);
+        game.apply(0, Action::EndTurn).expect("Ending the turn");
+        assert_eq!((game.current, game.turns, game.rolled), (0, 0, false));
+
+        next_roll(&mut game, false);
+        game.apply(0, Action::Roll).expect("Rolling again");
+        assert_eq!(game.doubles, 0);
+        game.apply(0, Action::EndTurn).expect("Ending the turn");
+        assert_eq!((game.current, game.turns), (1, 1));
+        assert!(!game.finished); // two rolls but only one turn
+    }
+
+    #[test]
+    fn third_doubles_go_to_jail() {
+        let mut game = rolling();
+        game.doubles = DOUBLES_LIMIT - 1;
+
+        next_roll(&mut game, true);
+        let events = game.apply(0, Action::Roll).expect("Rolling");
+
+        assert!(events.contains(&Event::Jailed { player: 0 }));
+        assert_eq!(game.players[0].position, game.jail());
+        assert_eq!(game.players[0].jailed, Some(0));
+        assert_eq!(game.doubles, 0);
+        game.apply(0, Action::EndTurn).expect("Ending the turn");
+        assert_eq!(game.current, 1);
+    }
+
+    #[test]
+    fn jailed_players_stay_until_doubles() {
+        let mut game = rolling();
+        game.players[0].position = game.jail();
+        game.players[0].jailed = Some(0);
+
+        next_roll(&mut game, false);
+        game.apply(0, Action::Roll).expect("Rolling in jail");
+        assert_eq!(game.players[0].jailed, Some(1));
+        assert_eq!(game.players[0].position, game.jail());
+        game.apply(0, Action::EndTurn).expect("Ending the turn");
+
+        game.current = 0;
+        game.rolled = false;
+        next_roll(&mut game, true);
+        let events = game.apply(0, Action::Roll).expect("Rolling in jail");
+        assert!(events.contains(&Event::LeftJail { player: 0 }));
+        assert!(events.iter().any(|event| matches!(event, Event::Moved { player: 0, .. })));
+        assert_eq!(game.doubles, 0); // doubles out of jail don't roll again
+        game.apply(0, Action::EndTurn).expect("Ending the turn");
+        assert_eq!(game.current, 1);
+    }
+
+    #[test]
+    fn last_failed_roll_forces_bail() {
+        let mut game = rolling();
+        game.players[0].position = game.jail();
+        game.players[0].jailed = Some(JAIL_ROLLS - 1);
+        game.players[0].money = 20;
+
+        next_roll(&mut game, false);
+        let events = game.apply(0, Action::Roll).expect("Rolling in jail");
+
+        assert!(events.contains(&Event::Payment { from: Some(0), to: None, amount: BAIL }));
+        assert!(events.contains(&Event::LeftJail { player: 0 }));
+        assert_eq!(game.players[0].jailed, None);
+        assert_eq!(game.players[0].debts, vec![(None, BAIL - 20)]); // even into debt
+        assert!(events.iter().any(|event| matches!(event, Event::Moved { player: 0, .. })));
+    }
+
+    #[test]
+    fn paying_bail() {
+        let mut game = rolling();
+        assert_eq!(game.apply(0, Action::PayBail), Err(Rejection::NotInJail));
+
+        game.players[0].jailed = Some(1);
+        game.players[0].money = BAIL - 1;
+        assert_eq!(game.apply(0, Action::PayBail), Err(Rejection::InsufficientFunds));
+
+        game.players[0].money = BAIL;
+        let events = game.apply(0, Action::PayBail).expect("Paying bail");
+        assert!(events.contains(&Event::LeftJail { player: 0 }));
+        assert_eq!(game.players[0].money, 0);
+        assert_eq!(game.players[0].jailed, None);
+
+        game.apply(0, Action::Roll).expect("Rolling after bail");
+        game.players[0].jailed = Some(0);
+        assert_eq!(game.apply(0, Action::PayBail), Err(Rejection::AlreadyRolled));
+    }
+
+    #[test]
+    fn using_a_jail_card_puts_it_back() {
+        let mut game = rolling();
+        game.players[0].jailed = Some(0);
+        assert_eq!(game.apply(0, Action::UseJailCard), Err(Rejection::NoJailCard));
+
+        game.decks[0].pile.retain(|card| *card != 7);
+        game.players[0].jail_cards.push((0, 7));
+        game.apply(0, Action::UseJailCard).expect("Using the card");
+
+        assert_eq!(game.players[0].jailed, None);
+        assert!(game.players[0].jail_cards.is_empty());
+        assert_eq!(game.decks[0].pile.back(), Some(&7));
+    }
+
+    // a one card deck, so the effect is known whatever the shuffle
+    fn drawing(effect: Effect) -> GameState {
+        let mut game = game(3);
+        game.decks = vec![Deck::new("Test", vec![card::Card::new("Test", effect)])];
+        game
+    }
+
+    #[test]
+    fn advancing_collects_go_on_the_way() {
+        let mut game = drawing(Effect::Advance(1));
+        let mut events = vec![];
+        game.players[0].position = 4;
+
+        game.draw(0, 0, &mut events);
+        assert_eq!(game.players[0].position, 1);
+        assert_eq!(game.players[0].money, STARTING_MONEY + GO_BONUS);
+
+        let mut events = vec![];
+        game.players[0].position = 0;
+        game.decks[0].cards[0].effect = Effect::Advance(2);
+        game.draw(0, 0, &mut events);
+        assert_eq!(game.players[0].position, 2);
+        assert!(!events.iter().any(|event| matches!(event, Event::PassedGo { .. })));
+    }
+
+    #[test]
+    fn going_back_lands_without_go() {
+        let mut game = drawing(Effect::Back(3));
+        let mut events = vec![];
+        game.tiles[4].owner = Some(1);
+        game.players[0].position = 1;
+
+        game.draw(0, 0, &mut events);
+
+        assert_eq!(game.players[0].position, 4);
+        assert!(!events.iter().any(|event| matches!(event, Event::PassedGo { .. })));
+        assert!(events.contains(&Event::PaidRent { player: 0, owner: 1, tile: 4, amount: 25 })); // lands like a roll
+    }
+
+    #[test]
+    fn paying_and_collecting_from_each_player() {
+        let mut game = drawing(Effect::PayEach(50));
+        let mut events = vec![];
+        game.players[2].bankrupt = true;
+
+        game.draw(0, 0, &mut events);
+        assert_eq!(game.players[0].money, STARTING_MONEY - 50);
+        assert_eq!(game.players[1].money, STARTING_MONEY + 50);
+        assert_eq!(game.players[2].money, STARTING_MONEY); // the bankrupt are left out
+
+        game.players[2].bankrupt = false;
+        game.decks[0].cards[0].effect = Effect::CollectFromEach(10);
+        game.draw(0, 0, &mut events);
+        assert_eq!(game.players[0].money, STARTING_MONEY - 30);
+        assert_eq!(game.players[1].money, STARTING_MONEY + 40);
+        assert_eq!(game.players[2].money, STARTING_MONEY - 10);
+    }
+
+    #[test]
+    fn repairs_charge_per_house_and_hotel() {
+        let mut game = drawing(Effect::Repairs { house: 25, hotel: 100 });
+        let mut events = vec![];
+        game.tiles[1].owner = Some(0);
+        game.tiles[1].set_houses(2);
+        game.tiles[2].owner = Some(0);
+        game.tiles[2].set_houses(HOTEL);
+        game.tiles[5].owner = Some(1);
+        game.tiles[5].set_houses(3);
+
+        game.draw(0, 0, &mut events);
+
+        assert_eq!(game.players[0].money, STARTING_MONEY - 150);
+        assert!(events.contains(&Event::Payment { from: Some(0), to: None, amount: 150 }));
+    }
+
+    #[test]
+    fn jail_cards_are_kept_until_used() {
+        let mut game = drawing(Effect::GetOutOfJail);
+        let mut events = vec![];
+
+        game.draw(0, 0, &mut events);
+        assert_eq!(game.players[0].jail_cards, vec![(0, 0)]);
+        assert!(game.decks[0].pile.is_empty());
+
+        let mut events = vec![];
+        game.draw(1, 0, &mut events); // nothing left to draw
+        assert!(events.is_empty());
+
+        game.players[0].jailed = Some(0);
+        game.apply(0, Action::UseJailCard).expect("Using the card");
+        assert_eq!(game.decks[0].pile, vec![0]);
+        assert!(game.players[0].jail_cards.is_empty());
+    }
+
+    #[test]
+    fn passing_go_pays_salary() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.players[0].position = 4;
+
+        game.advance(0, 3, &mut events);
+
+        assert_eq!(game.players[0].position, 1);
+        assert_eq!(game.players[0].money, STARTING_MONEY + GO_BONUS);
+        assert!(events.contains(&Event::PassedGo { player: 0, amount: GO_BONUS }));
+    }
+
+    #[test]
+    fn landing_on_go_pays_salary() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.players[0].position = 4;
+
+        game.advance(0, 2, &mut events);
+
+        assert_eq!(game.players[0].position, 0);
+        assert_eq!(game.players[0].money, STARTING_MONEY + GO_BONUS);
+    }
+
+    #[test]
+    fn rent_goes_to_the_owner() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.tiles[1].owner = Some(1);
+
+        game.pay_rent(0, 1, &mut events);
+
+        assert_eq!(game.players[0].money, STARTING_MONEY - 10);
+        assert_eq!(game.players[1].money, STARTING_MONEY + 10);
+        assert_eq!(events, vec![Event::PaidRent { player: 0, owner: 1, tile: 1, amount: 10 }]);
+    }
+
+    #[test]
+    fn rent_doubles_for_a_whole_group() {
+        let mut game = game(2);
+        game.tiles[1].owner = Some(1);
+        game.tiles[2].owner = Some(1);
+        assert_eq!(game.rent(1), 20);
+
+        game.tiles[1].set_houses(2);
+        assert_eq!(game.rent(1), 150);
+    }
+
+    #[test]
+    fn railroad_rent_goes_by_how_many_are_owned() {
+        let mut game = game(3);
+        game.tiles[2] = TileState::railroad(200, [25, 50, 100, 200, 200, 200]);
+        game.tiles[4].owner = Some(1);
+        assert_eq!(game.rent(4), 25);
+
+        game.tiles[2].owner = Some(1);
+        game.tiles[2].mortgaged = true; // still counts towards the set
+        assert_eq!(game.rent(4), 50);
+
+        game.tiles[2].owner = Some(2);
+        assert_eq!(game.rent(4), 25);
+    }
+
+    #[test]
+    fn utility_rent_multiplies_the_roll() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.tiles[1] = TileState::utility(150, [4, 10, 10, 10, 10, 10]);
+        game.tiles[2] = TileState::utility(150, [4, 10, 10, 10, 10, 10]);
+        game.tiles[1].owner = Some(1);
+        game.last_roll = 7;
+        assert_eq!(game.rent(1), 28);
+
+        game.tiles[2].owner = Some(1);
+        game.pay_rent(0, 1, &mut events);
+        assert_eq!(events, vec![Event::PaidRent { player: 0, owner: 1, tile: 1, amount: 70 }]);
+    }
+
+    #[test]
+    fn mortgaged_and_own_tiles_charge_no_rent() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.tiles[1].owner = Some(1);
+        game.tiles[1].mortgaged = true;
+        game.tiles[2].owner = Some(0);
+
+        game.pay_rent(0, 1, &mut events);
+        game.pay_rent(0, 2, &mut events);
+
+        assert_eq!(game.players[0].money, STARTING_MONEY);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn percent_tax_is_on_net_worth() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.tiles[5] = TileState::new(TileKind::Tax(Levy::Percent(10)), None, 0);
+        game.tiles[1].owner = Some(0);
+        game.tiles[1].set_houses(2);
+        game.tiles[2].owner = Some(0);
+        game.tiles[2].mortgaged = true;
+        assert_eq!(game.net_worth(0), STARTING_MONEY + 100 + 2 * 50 + 120 - 60);
+
+        game.players[0].position = 5;
+        game.land(0, &mut events);
+        assert_eq!(game.players[0].money, STARTING_MONEY - 126); // 10% of 1260
+
+        game.players[1].money = -500;
+        assert_eq!(game.tax(1, Levy::Percent(10)), 0);
+        assert_eq!(game.tax(1, Levy::Fixed(75)), 75);
+    }
+
+    #[test]
+    fn free_parking_pays_out_the_jackpot() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.tiles[5] = TileState::new(TileKind::FreeParking, None, 0);
+        game.settings.jackpot = true;
+
+        game.fine(0, 100, &mut events);
+        game.fine(1, BAIL, &mut events);
+        assert_eq!(game.jackpot, 150);
+
+        game.players[1].position = 5;
+        game.land(1, &mut events);
+        assert_eq!(game.jackpot, 0);
+        assert_eq!(game.players[1].money, STARTING_MONEY + 100);
+        assert!(events.contains(&Event::Payment { from: None, to: Some(1), amount: 150 }));
+    }
+
+    #[test]
+    fn fines_go_to_the_bank_without_the_jackpot_rule() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.tiles[5] = TileState::new(TileKind::FreeParking, None, 0);
+
+        game.fine(0, 100, &mut events);
+        game.players[0].position = 5;
+        game.land(0, &mut events);
+
+        assert_eq!(game.jackpot, 0);
+        assert_eq!(game.players[0].money, STARTING_MONEY - 100);
+    }
+
+    #[test]
+    fn buying_and_selling() {
+        let mut game = game(2);
+        game.rolled = true;
+        game.players[0].position = 1;
+
+        let events = game.apply(0, Action::Buy).expect("Buying an unowned tile");
+        assert_eq!(events, vec![Event::Bought { player: 0, tile: 1, price: 100 }]);
+        assert_eq!(game.tiles[1].owner, Some(0));
+        assert_eq!(game.players[0].money, STARTING_MONEY - 100);
+        assert_eq!(game.apply(1, Action::Buy), Err(Rejection::NotYourTurn));
+        assert_eq!(game.apply(0, Action::Buy), Err(Rejection::AlreadyOwned));
+
+        let events = game.apply(0, Action::Sell(1)).expect("Selling an owned tile");
+        assert_eq!(events, vec![Event::Sold { player: 0, tile: 1, price: 80 }]);
+        assert_eq!(game.tiles[1].owner, None);
+        assert_eq!(game.players[0].money, STARTING_MONEY - 20);
+    }
+
+    #[test]
+    fn buying_needs_a_roll_and_the_money() {
+        let mut game = game(2);
+        game.players[0].position = 4;
+        assert_eq!(game.apply(0, Action::Buy), Err(Rejection::NotRolled));
+
+        game.rolled = true;
+        game.players[0].money = 199;
+        assert_eq!(game.apply(0, Action::Buy), Err(Rejection::InsufficientFunds));
+        assert!(!game.available_actions(0).contains(&Action::Buy));
+    }
+
+    #[test]
+    fn bankruptcy_to_a_creditor_hands_everything_over() {
+        let mut game = game(3);
+        let mut events = vec![];
+        game.tiles[1].owner = Some(0);
+        game.tiles[1].mortgaged = true;
+        game.tiles[5].owner = Some(0);
+        game.tiles[5].set_houses(1);
+        game.players[0].jail_cards.push((0, 7));
+
+        game.bankrupt(0, Some(1), &mut events);
+
+        assert!(game.players[0].bankrupt);
+        assert_eq!(game.tiles[1].owner, Some(1));
+        assert!(game.tiles[1].mortgaged); // the creditor takes on the mortgage
+        assert_eq!(game.tiles[5].owner, Some(1));
+        assert_eq!(game.tiles[5].houses, 0);
+        assert_eq!(game.players[1].jail_cards, vec![(0, 7)]);
+        assert!(events.contains(&Event::Bankrupt { player: 0, creditor: Some(1) }));
+        assert!(!game.finished);
+    }
+
+    #[test]
+    fn bankruptcy_to_the_bank_frees_tiles() {
+        let mut game = game(3);
+        let mut events = vec![];
+        game.tiles[1].owner = Some(0);
+        game.tiles[1].mortgaged = true;
+        game.players[0].jail_cards.push((0, 7));
+        game.decks[0].pile.retain(|card| *card != 7);
+
+        game.bankrupt(0, None, &mut events);
+
+        assert_eq!(game.tiles[1].owner, None);
+        assert!(!game.tiles[1].mortgaged);
+        assert!(game.players[1].jail_cards.is_empty());
+        assert_eq!(game.decks[0].pile.back(), Some(&7));
+    }
+
+    #[test]
+    fn last_player_standing_wins() {
+        let mut game = game(2);
+        let mut events = vec![];
+
+        game.bankrupt(1, None, &mut events);
+
+        assert!(game.finished);
+        assert_eq!(events.last(), Some(&Event::GameOver { winner: Some(0) }));
+        assert_eq!(game.apply(0, Action::Roll), Err(Rejection::GameFinished));
+    }
+
+    // player 0 declines the railroad, starting an auction between everyone with money
+    fn auction(players: usize) -> GameState {
+        let mut game = game(players);
+        game.rolled = true;
+        game.players[0].position = 4;
+
+        let events = game.apply(0, Action::EndTurn).expect("Declining starts an auction");
+        assert_eq!(events, vec![Event::AuctionStarted { tile: 4 }]);
+        game
+    }
+
+    #[test]
+    fn auction_goes_to_the_highest_bidder() {
+        let mut game = auction(3);
+        assert_eq!(game.apply(0, Action::Roll), Err(Rejection::AuctionInProgress));
+
+        game.apply(1, Action::Bid(50)).expect("Opening bid");
+        assert_eq!(game.apply(2, Action::Bid(50)), Err(Rejection::BidTooLow));
+        game.apply(2, Action::Bid(60)).expect("Outbidding");
+        assert_eq!(game.apply(2, Action::PassBid), Err(Rejection::LeadingBid));
+        game.apply(0, Action::PassBid).expect("Passing");
+        let events = game.apply(1, Action::PassBid).expect("Last pass settles it");
+
+        assert!(events.contains(&Event::AuctionEnded { tile: 4, winner: Some(2), price: 60 }));
+        assert!(events.contains(&Event::TurnEnded { player: 0 }));
+        assert_eq!(game.auction, None);
+        assert_eq!(game.tiles[4].owner, Some(2));
+        assert_eq!(game.players[2].money, STARTING_MONEY - 60);
+        assert_eq!(game.current, 1);
+    }
+
+    #[test]
+    fn auction_without_bids_leaves_the_tile_unowned() {
+        let mut game = auction(2);
+
+        game.apply(1, Action::PassBid).expect("Passing");
+        let events = game.apply(0, Action::PassBid).expect("Everyone passed");
+
+        assert!(events.contains(&Event::AuctionEnded { tile: 4, winner: None, price: 0 }));
+        assert_eq!(game.tiles[4].owner, None);
+        assert_eq!(game.players[0].money, STARTING_MONEY);
+        assert_eq!(game.current, 1);
+    }
+
+    #[test]
+    fn auction_bids_are_capped_by_money() {
+        let mut game = auction(2);
+        game.players[1].money = 40;
+
+        assert_eq!(game.apply(1, Action::Bid(41)), Err(Rejection::InsufficientFunds));
+        assert_eq!(game.available_actions(1), vec![Action::Bid(1), Action::PassBid]);
+    }
+
+    #[test]
+    fn forfeiting_leader_voids_their_bid() {
+        let mut game = auction(3);
+
+        game.apply(1, Action::Bid(100)).expect("Opening bid");
+        game.apply(1, Action::Forfeit).expect("Forfeiting mid auction");
+        let auction = game.auction.as_ref().expect("Two bidders are left");
+        assert_eq!((auction.leader, auction.highest), (None, 0));
+
+        game.apply(2, Action::Bid(10)).expect("Bidding again");
+        let events = game.apply(0, Action::PassBid).expect("Last pass settles it");
+        assert!(events.contains(&Event::AuctionEnded { tile: 4, winner: Some(2), price: 10 }));
+        assert_eq!(game.current, 2); // the forfeited seat is skipped
+    }
+
+    fn offer(money: i32, tiles: Vec<usize>, jail_cards: usize) -> Offer {
+        Offer { money, tiles, jail_cards }
+    }
+
+    #[test]
+    fn accepted_trade_swaps_both_sides() {
+        let mut game = game(2);
+        game.tiles[1].owner = Some(0);
+        game.tiles[4].owner = Some(1);
+        game.players[1].jail_cards.push((1, 7));
+
+        game.apply(0, Action::ProposeTrade { to: 1, give: offer(50, vec![1], 0), take: offer(0, vec![4], 1) }).expect("Proposing");
+        let events = game.apply(1, Action::AcceptTrade(0)).expect("Accepting");
+
+        assert!(events.contains(&Event::TradeAccepted { trade: 0, from: 0, to: 1 }));
+        assert_eq!((game.tiles[1].owner, game.tiles[4].owner), (Some(1), Some(0)));
+        assert_eq!(game.players[0].money, STARTING_MONEY - 50);
+        assert_eq!(game.players[1].money, STARTING_MONEY + 50);
+        assert_eq!(game.players[0].jail_cards, vec![(1, 7)]);
+        assert!(game.trades.is_empty());
+    }
+
+    #[test]
+    fn trade_is_rechecked_on_accept_and_nothing_moves() {
+        let mut game = game(2);
+        game.tiles[4].owner = Some(1);
+
+        game.apply(0, Action::ProposeTrade { to: 1, give: offer(900, vec![], 0), take: offer(0, vec![4], 0) }).expect("Proposing");
+        game.players[0].money = 100; // spent what was offered since
+        let before = (game.players.clone(), game.tiles.clone());
+
+        assert_eq!(game.apply(1, Action::AcceptTrade(0)), Err(Rejection::InsufficientFunds));
+        assert_eq!((game.players.clone(), game.tiles.clone()), before);
+        assert_eq!(game.trades.len(), 1); // still pending, it can be rejected
+    }
+
+    #[test]
+    fn only_the_receiver_accepts() {
+        let mut game = game(3);
+        game.tiles[1].owner = Some(0);
+
+        game.apply(0, Action::ProposeTrade { to: 1, give: offer(0, vec![1], 0), take: offer(10, vec![], 0) }).expect("Proposing");
+
+        assert_eq!(game.apply(0, Action::AcceptTrade(0)), Err(Rejection::NotTrading));
+        assert_eq!(game.apply(2, Action::AcceptTrade(0)), Err(Rejection::NotTrading));
+        assert_eq!(game.apply(1, Action::AcceptTrade(1)), Err(Rejection::UnknownTrade));
+    }
+
+    #[test]
+    fn trades_with_built_groups_or_unowned_tiles_are_refused() {
+        let mut game = game(2);
+        game.tiles[1].owner = Some(0);
+        game.tiles[2].owner = Some(0);
+        game.tiles[2].set_houses(1);
+
+        let refused = |give: Offer| Action::ProposeTrade { to: 1, give, take: offer(10, vec![], 0) };
+        assert_eq!(game.clone().apply(0, refused(offer(0, vec![1], 0))), Err(Rejection::HasBuildings));
+        assert_eq!(game.clone().apply(0, refused(offer(0, vec![4], 0))), Err(Rejection::NotOwner));
+        assert_eq!(game.clone().apply(0, refused(offer(0, vec![], 1))), Err(Rejection::NoJailCard));
+        assert_eq!(game.apply(0, Action::ProposeTrade { to: 0, give: offer(10, vec![], 0), take: Offer::default() }), Err(Rejection::InvalidTrade));
+    }
+
+    #[test]
+    fn countering_replaces_the_trade() {
+        let mut game = game(2);
+        game.tiles[1].owner = Some(0);
+
+        game.apply(0, Action::ProposeTrade { to: 1, give: offer(0, vec![1], 0), take: offer(50, vec![], 0) }).expect("Proposing");
+        let events = game.apply(1, Action::CounterTrade { trade: 0, give: offer(30, vec![], 0), take: offer(0, vec![1], 0) }).expect("Countering");
+
+        assert_eq!(events, vec![
+            Event::TradeRejected { trade: 0, from: 0, to: 1 },
+            Event::TradeProposed { trade: 1, from: 1, to: 0 }
+        ]);
+        game.apply(0, Action::AcceptTrade(1)).expect("Accepting the counter");
+        assert_eq!(game.tiles[1].owner, Some(1));
+        assert_eq!(game.players[0].money, STARTING_MONEY + 30);
+    }
+
+    #[test]
+    fn bankruptcy_closes_pending_trades() {
+        let mut game = game(3);
+        let mut events = vec![];
+        game.tiles[1].owner = Some(0);
+        game.apply(0, Action::ProposeTrade { to: 1, give: offer(0, vec![1], 0), take: offer(50, vec![], 0) }).expect("Proposing");
+
+        game.bankrupt(1, None, &mut events);
+
+        assert!(game.trades.is_empty());
+        assert!(events.contains(&Event::TradeRejected { trade: 0, from: 0, to: 1 }));
+    }
+
+    // player 0 lands on player 1's tile with 5 left and owns the lone property to raise from
+    fn in_debt() -> GameState {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.rolled = true;
+        game.tiles[1].owner = Some(1);
+        game.tiles[5].owner = Some(0);
+        game.players[0].money = 5;
+        game.players[0].position = 1;
+
+        game.pay_rent(0, 1, &mut events);
+        assert_eq!(game.players[0].money, -5);
+        game
+    }
+
+    #[test]
+    fn ending_in_debt_raises_funds() {
+        let mut game = in_debt();
+
+        let events = game.apply(0, Action::EndTurn).expect("Ending in debt");
+        assert_eq!(events, vec![Event::RaisingFunds { player: 0, debt: 5 }]);
+        assert_eq!(game.raising, Some(0));
+        assert_eq!(game.available_actions(0), vec![Action::Sell(5), Action::Mortgage(5), Action::DeclareBankruptcy]);
+        assert!(game.available_actions(1).is_empty());
+        assert_eq!(game.apply(0, Action::EndTurn), Err(Rejection::RaisingFunds));
+        assert_eq!(game.apply(1, Action::ProposeTrade { to: 0, give: offer(10, vec![], 0), take: Offer::default() }), Err(Rejection::RaisingFunds));
+
+        let events = game.apply(0, Action::Mortgage(5)).expect("Mortgaging");
+        assert!(events.contains(&Event::DebtSettled { player: 0 }));
+        assert!(events.contains(&Event::TurnEnded { player: 0 }));
+        assert_eq!(game.raising, None);
+        assert_eq!(game.players[0].money, 70);
+        assert_eq!(game.current, 1);
+    }
+
+    #[test]
+    fn declaring_bankruptcy_pays_the_creditor_only_what_was_there() {
+        let mut game = in_debt();
+
+        game.apply(0, Action::EndTurn).expect("Ending in debt");
+        let events = game.apply(0, Action::DeclareBankruptcy).expect("Giving up");
+
+        assert!(events.contains(&Event::Payment { from: Some(1), to: Some(0), amount: 5 }));
+        assert!(events.contains(&Event::Bankrupt { player: 0, creditor: Some(1) }));
+        assert_eq!(game.players[1].money, STARTING_MONEY + 5); // the 5 player 0 actually had
+        assert_eq!(game.tiles[5].owner, Some(1));
+        assert!(game.finished);
+    }
+
+    #[test]
+    fn nothing_left_to_raise_is_bankruptcy() {
+        let mut game = in_debt();
+        game.tiles[5].owner = None;
+
+        let events = game.apply(0, Action::EndTurn).expect("Ending in debt");
+
+        assert!(!events.iter().any(|event| matches!(event, Event::RaisingFunds { .. })));
+        assert!(events.contains(&Event::Bankrupt { player: 0, creditor: Some(1) }));
+        assert_eq!(game.players[1].money, STARTING_MONEY + 5);
+    }
+
+    #[test]
+    fn creditors_only_give_back_what_they_were_short_paid() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.tiles[1].owner = Some(1);
+        game.players[0].money = 30;
+        game.fine(0, BAIL, &mut events); // 20 short to the bank
+        game.pay_rent(0, 1, &mut events); // then 10 short to player 1
+        assert_eq!(game.players[0].debts, vec![(None, 20), (Some(1), 10)]);
+
+        game.bankrupt(0, game.players[0].creditor(), &mut events);
+
+        assert!(events.contains(&Event::Payment { from: Some(1), to: Some(0), amount: 10 }));
+        assert_eq!(game.players[1].money, STARTING_MONEY); // had the 10 back, the bank writes off its 20
+    }
+
+    #[test]
+    fn raised_money_pays_the_oldest_debts_first() {
+        let mut game = game(3);
+        let mut events = vec![];
+        game.players[0].money = 0;
+        game.transfer(Some(0), Some(1), 10, &mut events);
+        game.transfer(Some(0), Some(2), 10, &mut events);
+        game.players[0].money += 15; // raised, clears player 1 and half of player 2
+
+        game.bankrupt(0, game.players[0].creditor(), &mut events);
+
+        assert_eq!(game.players[1].money, STARTING_MONEY + 10);
+        assert_eq!(game.players[2].money, STARTING_MONEY + 5);
+        assert!(events.contains(&Event::Bankrupt { player: 0, creditor: Some(2) }));
+    }
+
+    #[test]
+    fn settled_debts_are_forgotten() {
+        let mut game = game(2);
+        let mut events = vec![];
+        game.players[0].money = 5;
+        game.transfer(Some(0), Some(1), 10, &mut events);
+        game.players[0].money += 20;
+        game.fine(0, 20, &mut events);
+
+        assert_eq!(game.players[0].debts, vec![(None, 5)]);
+        assert_eq!(game.players[0].creditor(), None);
+    }
+}
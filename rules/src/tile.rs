@@ -0,0 +1,139 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tier {
+    #[default]
+    None,
+    Owned,
+    House,
+    Hotel
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TileKind {
+    Property,
+    Railroad, // rent by how many railroads the owner has
+    Utility, // rent is a multiplier, by how many utilities the owner has, times the dice roll
+    Chance(usize), // index into GameState.decks
+    Go, // salary comes from Settings
+    Tax(Levy),
+    FreeParking,
+    Jail, // just visiting unless jailed
+    GoToJail,
+    Corner // blank, for boards with more than four corners
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Levy {
+    Fixed(i32),
+    Percent(i32) // of the player's net worth
+}
+
+pub const HOTEL: usize = 5; // a hotel is the fifth house
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TileState {
+    pub kind: TileKind,
+    pub group: Option<usize>,
+    pub cost: i32,
+    pub rent: [i32; HOTEL + 1], // by house count, [0] is the bare rent. Railroads and utilities index by how many the owner has instead
+    pub house_cost: i32,
+    pub owner: Option<usize>, // index into GameState.players
+    pub mortgaged: bool,
+    pub houses: usize,
+    pub tier: Tier
+}
+
+impl TileState {
+    pub fn new(kind: TileKind, group: Option<usize>, cost: i32) -> Self {
+        Self {
+            kind,
+            group,
+            cost,
+            rent: [0; HOTEL + 1],
+            house_cost: 0,
+            owner: None,
+            mortgaged: false,
+            houses: 0,
+            tier: Tier::None
+        }
+    }
+
+    pub fn property(group: usize, cost: i32, rent: [i32; HOTEL + 1], house_cost: i32) -> Self {
+        Self {
+            rent,
+            house_cost,
+            ..Self::new(TileKind::Property, Some(group), cost)
+        }
+    }
+
+    // rent and multipliers start from owning one, the last entry covers owning more
+    pub fn railroad(cost: i32, rent: [i32; HOTEL + 1]) -> Self {
+        Self {
+            rent,
+            ..Self::new(TileKind::Railroad, None, cost)
+        }
+    }
+
+    pub fn utility(cost: i32, multipliers: [i32; HOTEL + 1]) -> Self {
+        Self {
+            rent: multipliers,
+            ..Self::new(TileKind::Utility, None, cost)
+        }
+    }
+
+    pub fn is_ownable(&self) -> bool {
+        matches!(self.kind, TileKind::Property | TileKind::Railroad | TileKind::Utility)
+    }
+
+    pub fn set_houses(&mut self, houses: usize) {
+        self.houses = houses;
+        self.tier = match houses {
+            0 if self.owner.is_some() => Tier::Owned,
+            0 => Tier::None,
+            HOTEL => Tier::Hotel,
+            _ => Tier::House
+        };
+    }
+}
+
+// Placeholder layout: a corner to start each side, a chance every third tile, properties grouped in threes
+// Chance tiles alternate between the chance and community chest decks, income tax and luxury tax sit on the first and last sides
+pub fn generate(corners: usize, side: usize) -> Vec<TileState> {
+    let mut tiles = vec![];
+    let mut group_fill = 0;
+    let mut chance_fill = 0;
+
+    for tile in 0..(corners * side) {
+        let relative_tile = tile % side;
+        if relative_tile == 0 {
+            let kind = match tile / side {
+                0 => TileKind::Go,
+                1 => TileKind::Jail,
+                2 => TileKind::FreeParking,
+                3 => TileKind::GoToJail,
+                _ => TileKind::Corner
+            };
+            tiles.push(TileState::new(kind, None, 100));
+            continue;
+        }
+        if tile == side / 2 {
+            tiles.push(TileState::new(TileKind::Tax(Levy::Percent(10)), None, 100));
+            continue;
+        }
+        if tile == corners * side - side / 2 {
+            tiles.push(TileState::new(TileKind::Tax(Levy::Fixed(100)), None, 100));
+            continue;
+        }
+        if relative_tile % 3 == 1 {
+            tiles.push(TileState::new(TileKind::Chance(chance_fill % 2), None, 100));
+            chance_fill += 1;
+            continue;
+        }
+
+        tiles.push(TileState::property(group_fill / 3, 100, [100, 200, 300, 400, 500, 600], 50));
+        group_fill += 1;
+    }
+
+    tiles
+}
@@ -0,0 +1,31 @@
+use serde::{Serialize, Deserialize};
+
+// What one side of a trade hands over
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Offer {
+    pub money: i32,
+    pub tiles: Vec<usize>,
+    pub jail_cards: usize
+}
+
+impl Offer {
+    pub fn is_empty(&self) -> bool {
+        self.money == 0 && self.tiles.is_empty() && self.jail_cards == 0
+    }
+}
+
+// A pending proposal, only the receiver can accept but either side can reject
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trade {
+    pub id: usize,
+    pub from: usize,
+    pub to: usize,
+    pub give: Offer, // from the proposer
+    pub take: Offer // from the receiver
+}
+
+impl Trade {
+    pub fn involves(&self, player: usize) -> bool {
+        self.from == player || self.to == player
+    }
+}
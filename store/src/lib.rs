@@ -20,8 +20,17 @@ pub fn protocol_builder() -> Protocol {
 
         .add_component::<tile::Group>()
         .add_component::<tile::ServerSide>()
+        .add_component::<tile::Railroad>()
+        .add_component::<tile::Utility>()
         .add_component::<tile::Chance>()
+        .add_component::<tile::Deck>()
+        .add_component::<tile::Title>()
         .add_component::<tile::Corner>()
+        .add_component::<tile::Go>()
+        .add_component::<tile::Tax>()
+        .add_component::<tile::FreeParking>()
+        .add_component::<tile::Jail>()
+        .add_component::<tile::GoToJail>()
         .add_component::<tile::Tile>()
 
         // Channels
@@ -38,8 +47,20 @@ pub fn protocol_builder() -> Protocol {
         // Messages
         .add_message::<transfer::AlterOwnable>()
         .add_message::<transfer::SellOwnable>()
+        .add_message::<transfer::MortgageOwnable>()
+        .add_message::<transfer::UnmortgageOwnable>()
         .add_message::<transfer::BuyOwnable>()
         .add_message::<transfer::EndTurn>()
+        .add_message::<transfer::DeclareBankruptcy>()
+        .add_message::<transfer::PlacedBid>()
+        .add_message::<transfer::PassBid>()
+        .add_message::<transfer::ProposeTrade>()
+        .add_message::<transfer::AcceptTrade>()
+        .add_message::<transfer::RejectTrade>()
+        .add_message::<transfer::CounterTrade>()
+        .add_message::<transfer::RollDice>()
+        .add_message::<transfer::PayBail>()
+        .add_message::<transfer::UseJailCard>()
         .add_message::<transfer::Forfeit>() // Realistically only available to human players
         .add_message::<transfer::Ready>()
         .add_message::<transfer::Finish>()
@@ -47,6 +68,13 @@ pub fn protocol_builder() -> Protocol {
         .add_message::<transfer::BeginTurn>()
         .add_message::<transfer::SendPlayer>()
         .add_message::<transfer::StartGame>()
+        .add_message::<transfer::TurnTimer>()
+        .add_message::<transfer::DrawCard>()
+        .add_message::<transfer::ActionRejected>()
+        .add_message::<transfer::AuctionUpdate>()
+        .add_message::<transfer::EndAuction>()
+        .add_message::<transfer::TradeOffered>()
+        .add_message::<transfer::TradeClosed>()
         .add_message::<transfer::IssueReward>()
         .add_message::<transfer::EndGame>() // for resetting gamestate on clients
 
@@ -14,12 +14,14 @@ impl Money {
 
 #[derive(Component, Replicate)]
 pub struct Position {
-    pub tile: Property<u64>
+    pub tile: Property<u64>,
+    pub jailed: Property<bool>, // on the jail tile without just visiting
+    pub jail_cards: Property<usize>
 }
 
 impl Position {
     pub fn new(tile: u64) -> Self {
-        Position::new_complete(tile)
+        Position::new_complete(tile, false, 0)
     }
 }
 
@@ -31,6 +33,12 @@ pub enum Action {
     Sell, // Currently can only sell 1 at a time for the AI
     // SellMany,
     Purchase,
+    Alter, // Build or sell houses, the client picks the tile
+    Roll, // Only offered before moving from jail, otherwise the board rolls
+    PayBail,
+    UseJailCard,
+    Mortgage, // Mortgage or unmortgage, the client picks the tile
+    DeclareBankruptcy, // Only offered while raising funds, with no way to end the turn
     // More later
 }
 
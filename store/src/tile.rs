@@ -27,16 +27,115 @@ impl ServerSide {
 
 
 #[derive(Component, Replicate)]
-pub struct Chance;
+pub struct Railroad {
+    pub rent: Property<Vec<i32>>, // by how many railroads the owner has, [0] is just this one
+    pub owned: Property<usize> // railroads the owner has, 0 while unowned
+}
+
+impl Railroad {
+    pub fn new(rent: Vec<i32>) -> Self {
+        Railroad::new_complete(rent, 0)
+    }
+}
+
+#[derive(Component, Replicate)]
+pub struct Utility {
+    pub multipliers: Property<Vec<i32>>, // times the dice roll, by how many utilities the owner has
+    pub owned: Property<usize>
+}
+
+impl Utility {
+    pub fn new(multipliers: Vec<i32>) -> Self {
+        Utility::new_complete(multipliers, 0)
+    }
+}
+
+#[derive(Component, Replicate)]
+pub struct Chance {
+    pub deck: Property<usize> // matches Deck.index
+}
+
+impl Chance {
+    pub fn new(deck: usize) -> Self {
+        Chance::new_complete(deck)
+    }
+}
+
+#[derive(Component, Replicate)]
+pub struct Deck {
+    pub index: Property<usize>,
+    pub name: Property<String>,
+    pub cards: Property<Vec<String>> // card text, DrawCard.card indexes into this
+}
+
+impl Deck {
+    pub fn new(index: usize, name: String, cards: Vec<String>) -> Self {
+        Deck::new_complete(index, name, cards)
+    }
+}
+
+#[derive(Component, Replicate)]
+pub struct Title {
+    pub name: Property<String>,
+    pub color: Property<Option<String>> // "#rrggbb", only boards loaded from a file have these
+}
+
+impl Title {
+    pub fn new(name: String, color: Option<String>) -> Self {
+        Title::new_complete(name, color)
+    }
+}
+
+#[derive(Component, Replicate)]
+pub struct Corner; // every corner also gets its own kind below, unless it is blank
+
+#[derive(Component, Replicate)]
+pub struct Go {
+    pub salary: Property<i32> // paid for passing or landing
+}
+
+impl Go {
+    pub fn new(salary: i32) -> Self {
+        Go::new_complete(salary)
+    }
+}
+
+#[derive(Component, Replicate)]
+pub struct Tax {
+    pub amount: Property<i32>,
+    pub percentage: Property<bool> // amount is a percentage of net worth instead of a fixed fee
+}
+
+impl Tax {
+    pub fn new(amount: i32, percentage: bool) -> Self {
+        Tax::new_complete(amount, percentage)
+    }
+}
+
+#[derive(Component, Replicate)]
+pub struct FreeParking {
+    pub jackpot: Property<Option<i32>> // None without the jackpot house rule
+}
+
+impl FreeParking {
+    pub fn new(jackpot: Option<i32>) -> Self {
+        FreeParking::new_complete(jackpot)
+    }
+}
+
+#[derive(Component, Replicate)]
+pub struct Jail; // just visiting unless Position.jailed
 
 #[derive(Component, Replicate)]
-pub struct Corner;
+pub struct GoToJail;
 
 #[derive(Component, Replicate)]
 pub struct Tile {
     pub tier: Property<Tier>,
     pub owner: Property<Option<u64>>, // u64 is the owner's entity
-    pub cost: Property<i32>
+    pub cost: Property<i32>,
+    pub houses: Property<u8>, // 5 is a hotel
+    pub mortgaged: Property<bool> // still owned, but collects no rent
     // add more tile information later
 }
 
@@ -51,6 +150,6 @@ pub enum Tier {
 
 impl Tile {
     pub fn new(tier: Tier, owner: Option<u64>, cost: i32) -> Self {
-        Tile::new_complete(tier, owner, cost)
+        Tile::new_complete(tier, owner, cost, 0, false)
     }
 }
\ No newline at end of file
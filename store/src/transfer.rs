@@ -1,6 +1,14 @@
-use naia_bevy_shared::{Channel, Message};
+use naia_bevy_shared::{Channel, Message, Serde};
 use crate::player::Action;
 
+// One side of a trade, tiles are server ids
+#[derive(Default, Clone, PartialEq, Serde)]
+pub struct TradeOffer {
+    pub money: i32,
+    pub tiles: Vec<u64>,
+    pub jail_cards: usize
+}
+
 #[derive(Channel)]
 pub struct PlayerActionChannel;
 
@@ -10,8 +18,7 @@ pub struct Forfeit; // Not going to let the AI forfeit, less data
 #[derive(Message)]
 pub struct AlterOwnable {
     pub id: u64,
-    pub house: bool,
-    pub hotel: bool
+    pub build: bool // false sells a house back, the fifth house is a hotel
 }
 
 #[derive(Message)]
@@ -19,11 +26,65 @@ pub struct SellOwnable {
     pub id: u64
 }
 
+#[derive(Message)]
+pub struct MortgageOwnable {
+    pub id: u64
+}
+
+#[derive(Message)]
+pub struct UnmortgageOwnable { // costs the mortgage plus interest
+    pub id: u64
+}
+
 #[derive(Message)]
 pub struct BuyOwnable;
 
 #[derive(Message)]
-pub struct EndTurn;
+pub struct EndTurn; // declining to buy starts an auction before the turn ends
+
+#[derive(Message)]
+pub struct DeclareBankruptcy; // gives up while raising funds, the creditor takes everything
+
+#[derive(Message)]
+pub struct PlacedBid {
+    pub amount: i32
+}
+
+#[derive(Message)]
+pub struct PassBid;
+
+#[derive(Message)]
+pub struct ProposeTrade { // allowed at any point outside an auction
+    pub to: u64,
+    pub give: TradeOffer,
+    pub take: TradeOffer
+}
+
+#[derive(Message)]
+pub struct AcceptTrade {
+    pub id: u64
+}
+
+#[derive(Message)]
+pub struct RejectTrade { // the proposer can also withdraw
+    pub id: u64
+}
+
+#[derive(Message)]
+pub struct CounterTrade {
+    pub id: u64,
+    pub give: TradeOffer,
+    pub take: TradeOffer
+}
+
+#[derive(Message)]
+pub struct RollDice; // try for doubles from jail
+
+#[derive(Message)]
+pub struct PayBail;
+
+#[derive(Message)]
+pub struct UseJailCard;
 
 #[derive(Message)]
 pub struct Ready;
@@ -38,7 +99,7 @@ pub struct BoardUpdateChannel;
 pub struct BeginTurn { 
     // in theory we can make the client simpler by not having replication, just using begin turn to transfer necessary data
     // however, its nice to have access to a lot of information for the AI paramters
-    pub available_actions: Vec<Action> // forces synchronous playing--going to have to change this later for suddeb debt and trading, make this Vec<Vec> soon
+    pub available_actions: Vec<Action> // forces synchronous playing--going to have to change this later for suddeb debt, trades have their own messages
 }
 
 #[derive(Message)]
@@ -47,7 +108,57 @@ pub struct SendPlayer {
 }
 
 #[derive(Message)]
-pub struct StartGame;
+pub struct StartGame {
+    pub seed: u64 // replays the game's dice and cards
+}
+
+#[derive(Message)]
+pub struct TurnTimer { // to the current player as their turn starts, and again shortly before the board plays it for them
+    pub seconds: f32, // left on the clock
+    pub warning: bool
+}
+
+#[derive(Message)]
+pub struct ActionRejected { // anything illegal or not offered in the last BeginTurn
+    pub reason: String
+}
+
+#[derive(Message)]
+pub struct AuctionUpdate { // sent to every player whenever the auction changes
+    pub tile: u64,
+    pub highest: i32,
+    pub leader: Option<u64>,
+    pub bidding: bool // whether the receiver can still bid
+}
+
+#[derive(Message)]
+pub struct EndAuction {
+    pub tile: u64,
+    pub winner: Option<u64>,
+    pub price: i32
+}
+
+#[derive(Message)]
+pub struct TradeOffered { // sent to both sides
+    pub id: u64,
+    pub from: u64,
+    pub to: u64,
+    pub give: TradeOffer,
+    pub take: TradeOffer
+}
+
+#[derive(Message)]
+pub struct TradeClosed {
+    pub id: u64,
+    pub accepted: bool
+}
+
+#[derive(Message)]
+pub struct DrawCard {
+    pub player: u64,
+    pub deck: usize,
+    pub card: usize
+}
 
 #[derive(Message)]
 pub struct EndGame;